@@ -0,0 +1,21 @@
+use std::any::Any;
+
+use autd3::{
+    core::link::Link,
+    link::{Audit, audit::version::V12_1},
+    prelude::*,
+};
+
+pub fn emulators<L: Link + 'static>(autd: &Controller<L, firmware::V12_1>) -> Option<&[V12_1]> {
+    (autd.link() as &dyn Any)
+        .downcast_ref::<Audit<V12_1>>()
+        .map(|audit| audit.as_slice())
+}
+
+pub fn emulators_mut<L: Link + 'static>(
+    autd: &mut Controller<L, firmware::V12_1>,
+) -> Option<&mut [V12_1]> {
+    (autd.link_mut() as &mut dyn Any)
+        .downcast_mut::<Audit<V12_1>>()
+        .map(|audit| audit.as_mut_slice())
+}
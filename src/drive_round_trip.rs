@@ -0,0 +1,104 @@
+use crate::{audit, print_msg_and_wait_for_key};
+
+use autd3::{core::link::Link, prelude::*};
+
+fn pulse_width(intensity: Intensity) -> PulseWidth<9, u16> {
+    PulseWidth::from_duty((intensity.0 as f32 / 255.).asin() / PI).unwrap()
+}
+
+fn drive(dev_idx: usize, tr_idx: usize, step: usize) -> Drive {
+    Drive {
+        phase: Phase((255 - step + tr_idx + dev_idx) as u8),
+        intensity: Intensity((step + tr_idx) as u8),
+    }
+}
+
+pub fn drive_round_trip_test<L: Link + 'static>(
+    autd: &mut Controller<L, firmware::V12_1>,
+) -> anyhow::Result<()> {
+    autd.send((Static::default(), Silencer::disable()))?;
+    autd.send(PulseWidthEncoder::default())?;
+
+    if let Some(emulators) = audit::emulators(autd) {
+        emulators.iter().for_each(|cpu| {
+            assert_eq!(
+                (0..=255)
+                    .map(|i| pulse_width(Intensity(i)))
+                    .collect::<Vec<_>>(),
+                cpu.fpga().pulse_width_encoder_table()
+            );
+        });
+    }
+
+    let sampled = [(0, 248), (1, 247), (123, 124)];
+    let sampled_steps = [0, 64, 128, 192, 255];
+
+    (0..=255).try_for_each(|step| -> anyhow::Result<()> {
+        let sample = sampled_steps
+            .iter()
+            .position(|&s| s == step)
+            .map(|i| sampled[i % sampled.len()]);
+        if let Some((a, b)) = sample {
+            autd.send(GPIOOutputs::new(move |dev, gpio| match gpio {
+                GPIOOut::O0 => Some(GPIOOutputType::PwmOut(&dev[a])),
+                GPIOOut::O1 => Some(GPIOOutputType::PwmOut(&dev[b])),
+                _ => None,
+            }))?;
+        }
+
+        autd.send(autd3::gain::Custom::new(move |dev| {
+            let dev_idx = dev.idx();
+            move |tr| drive(dev_idx, tr.idx(), step)
+        }))?;
+
+        match audit::emulators(autd) {
+            Some(emulators) => emulators.iter().for_each(|cpu| {
+                let drives = cpu.fpga().drives_at(Segment::S0, 0);
+                assert_eq!(cpu.num_transducers(), drives.len());
+                drives.iter().enumerate().for_each(|(tr_idx, &d)| {
+                    let expect = drive(cpu.idx(), tr_idx, step);
+                    assert_eq!(
+                        expect,
+                        d,
+                        "device {}, transducer {}, step {}",
+                        cpu.idx(),
+                        tr_idx,
+                        step
+                    );
+                    assert_eq!(
+                        pulse_width(expect.intensity),
+                        cpu.fpga().to_pulse_width(d.intensity, 0xFF),
+                        "device {}, transducer {}, step {}",
+                        cpu.idx(),
+                        tr_idx,
+                        step
+                    );
+                });
+            }),
+            None => {
+                if let Some((a, b)) = sample {
+                    let duty = |tr_idx| {
+                        pulse_width(drive(0, tr_idx, step).intensity).pulse_width() as f32 / 512.
+                            * 100.
+                    };
+                    print_msg_and_wait_for_key(&format!(
+                        "各デバイスのGPIO[0]出力(振動子{}), GPIO[1]出力(振動子{})矩形波のDuty比がそれぞれ{:.2}%, {:.2}%であること",
+                        a,
+                        b,
+                        duty(a),
+                        duty(b)
+                    ));
+                }
+            }
+        }
+
+        Ok(())
+    })?;
+
+    autd.send(GPIOOutputs::new(|_dev, gpio| match gpio {
+        GPIOOut::O0 => Some(GPIOOutputType::BaseSignal),
+        _ => None,
+    }))?;
+
+    Ok(())
+}
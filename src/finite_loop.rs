@@ -0,0 +1,235 @@
+use std::{num::NonZeroU16, time::Duration};
+
+use crate::{
+    assert_no_mismatches, audit, print_msg_and_wait_for_key, target::Target, trajectory::Trajectory,
+};
+
+use autd3::{
+    core::{common::ULTRASOUND_PERIOD, link::Link},
+    prelude::*,
+};
+
+const CYCLE: usize = 4;
+const COUNTS: [u16; 4] = [2, 3, 255, u16::MAX];
+// On hardware, counts up to this are observed with a lap of 0.5s so that the operator can count the
+// pulses; larger counts run with the shortest lap and are checked on a scope
+const COUNTABLE_MAX: u16 = 3;
+const COUNTABLE_DIVIDE: u16 = 5000;
+
+fn send<L: Link>(
+    autd: &mut Controller<L, firmware::V12_1>,
+    target: Target,
+    loop_behavior: LoopBehavior,
+    segment: Segment,
+    transition_mode: TransitionMode,
+    divide: NonZeroU16,
+) -> anyhow::Result<()> {
+    let config = SamplingConfig::new(divide);
+    let center = autd.geometry().center() + Vector3::new(0., 0., 150.0 * mm);
+    let points = Trajectory::circle(center, 30.0 * mm).points(CYCLE);
+    let transition_mode = Some(transition_mode);
+    match target {
+        Target::Modulation => autd.send(WithLoopBehavior {
+            inner: autd3::modulation::Custom {
+                buffer: (0..CYCLE).map(|i| (0xFF - i * 0x10) as u8).collect(),
+                sampling_config: config,
+            },
+            loop_behavior,
+            segment,
+            transition_mode,
+        })?,
+        Target::FociSTM => autd.send(WithLoopBehavior {
+            inner: FociSTM::new(points, config),
+            loop_behavior,
+            segment,
+            transition_mode,
+        })?,
+        Target::GainSTM => autd.send(WithLoopBehavior {
+            inner: GainSTM::new(
+                points
+                    .into_iter()
+                    .map(|p| Focus::new(p, Default::default()))
+                    .collect::<Vec<_>>(),
+                config,
+                Default::default(),
+            ),
+            loop_behavior,
+            segment,
+            transition_mode,
+        })?,
+    }
+    Ok(())
+}
+
+fn check_count<L: Link + 'static>(
+    autd: &mut Controller<L, firmware::V12_1>,
+    target: Target,
+    count: u16,
+) -> anyhow::Result<Vec<String>> {
+    let emulated = audit::emulators(autd).is_some();
+    let divide = if !emulated && count <= COUNTABLE_MAX {
+        NonZeroU16::new(COUNTABLE_DIVIDE).unwrap()
+    } else {
+        NonZeroU16::MIN
+    };
+    let gpio = match target {
+        Target::Modulation => 0,
+        _ => 1,
+    };
+    send(
+        autd,
+        target,
+        LoopBehavior::Infinite,
+        Segment::S0,
+        TransitionMode::Immediate,
+        divide,
+    )?;
+
+    let sample = ULTRASOUND_PERIOD * divide.get() as u32;
+    if !emulated {
+        print_msg_and_wait_for_key(&if count <= COUNTABLE_MAX {
+            format!(
+                "Enterを押した約1秒後から, 各デバイスのGPIO[{}]のパルスを数える",
+                gpio
+            )
+        } else {
+            format!(
+                "オシロスコープを各デバイスのGPIO[{}]の立ち上がりでシングルトリガに設定し, Enterを押す",
+                gpio
+            )
+        });
+    }
+    let lap = sample * CYCLE as u32;
+    // Start on a lap boundary, so that the first repetition begins at index 0
+    let lap_ns = lap.as_nanos() as u64;
+    let start = (DcSysTime::now().sys_time() + Duration::from_secs(1).as_nanos() as u64)
+        .div_ceil(lap_ns)
+        * lap_ns;
+    let start = DcSysTime::ZERO + Duration::from_nanos(start);
+    send(
+        autd,
+        target,
+        LoopBehavior::Finite(NonZeroU16::new(count).unwrap()),
+        Segment::S1,
+        TransitionMode::SysTime(start),
+        divide,
+    )?;
+
+    let mut mismatches = Vec::new();
+    match audit::emulators_mut(autd) {
+        Some(emulators) => emulators.iter_mut().for_each(|cpu| {
+            let count = count as u32;
+            let half = sample * (CYCLE / 2) as u32;
+            [
+                (start - Duration::from_nanos(1), Segment::S0, None),
+                (start, Segment::S1, Some(0)),
+                (start + lap + half, Segment::S1, Some(CYCLE / 2)),
+                (start + lap * (count - 1) + half, Segment::S1, Some(CYCLE / 2)),
+                (start + lap * count - Duration::from_nanos(1), Segment::S1, Some(CYCLE - 1)),
+                (start + lap * count + half, Segment::S1, Some(CYCLE - 1)),
+                (start + lap * (count + 2) + half, Segment::S1, Some(CYCLE - 1)),
+            ]
+            .into_iter()
+            .for_each(|(t, expect_segment, expect_idx)| {
+                cpu.update_with_sys_time(t);
+                let fpga = cpu.fpga();
+                let (segment, idx) = match target {
+                    Target::Modulation => (fpga.current_mod_segment(), fpga.current_mod_idx()),
+                    _ => (fpga.current_stm_segment(), fpga.current_stm_idx()),
+                };
+                if segment != expect_segment
+                    || expect_idx.is_some_and(|expect_idx| idx != expect_idx)
+                {
+                    mismatches.push(format!(
+                        "{:?}, Finite({}): device {} at start{:+?} expected {:?}/{:?}, actual {:?}/{}",
+                        target,
+                        count,
+                        cpu.idx(),
+                        t.sys_time() as i64 - start.sys_time() as i64,
+                        expect_segment,
+                        expect_idx,
+                        segment,
+                        idx
+                    ));
+                }
+            });
+            if target == Target::Modulation
+                && cpu.fpga().modulation_buffer(Segment::S1)[cpu.fpga().current_mod_idx()]
+                    != (0xFF - (CYCLE - 1) * 0x10) as u8
+            {
+                mismatches.push(format!(
+                    "{:?}, Finite({}): device {} does not hold the last sample",
+                    target,
+                    count,
+                    cpu.idx()
+                ));
+            }
+        }),
+        None => {
+            let end = start + lap * count as u32;
+            std::thread::sleep(Duration::from_nanos(
+                end.sys_time().saturating_sub(DcSysTime::now().sys_time()),
+            ));
+            print_msg_and_wait_for_key(&if count <= COUNTABLE_MAX {
+                format!(
+                    "各デバイスのGPIO[{}]のパルスが{:?}間隔で{}回出力された後, 停止していること",
+                    gpio, lap, count
+                )
+            } else {
+                format!(
+                    "各デバイスのGPIO[{}]のパルス列 ({:?}間隔) が{:?}間続いた後, 停止していること",
+                    gpio,
+                    lap,
+                    lap * count as u32
+                )
+            });
+        }
+    }
+
+    std::thread::sleep(Duration::from_millis(100));
+    autd.fpga_state()?.iter().for_each(|state| {
+        assert!(state.is_some());
+        let state = state.unwrap();
+        let segment = match target {
+            Target::Modulation => Some(state.current_mod_segment()),
+            _ => state.current_stm_segment(),
+        };
+        if segment != Some(Segment::S1) {
+            mismatches.push(format!(
+                "{:?}, Finite({}): current segment {:?}",
+                target, count, segment
+            ));
+        }
+    });
+
+    Ok(mismatches)
+}
+
+pub fn finite_loop_test<L: Link + 'static>(
+    autd: &mut Controller<L, firmware::V12_1>,
+) -> anyhow::Result<()> {
+    autd.send(Silencer::disable())?;
+    autd.send(GPIOOutputs::new(|_dev, gpio| match gpio {
+        GPIOOut::O0 => Some(GPIOOutputType::ModIdx(0)),
+        GPIOOut::O1 => Some(GPIOOutputType::StmIdx(0)),
+        _ => None,
+    }))?;
+    autd.send(Static::default())?;
+
+    let mut mismatches = Vec::new();
+    for target in [Target::Modulation, Target::FociSTM, Target::GainSTM] {
+        for count in COUNTS {
+            mismatches.extend(check_count(autd, target, count)?);
+        }
+    }
+
+    assert_no_mismatches(&mismatches, "finite loop test");
+
+    autd.send(GPIOOutputs::new(|_dev, gpio| match gpio {
+        GPIOOut::O0 => Some(GPIOOutputType::BaseSignal),
+        _ => None,
+    }))?;
+    autd.send(Clear::new())?;
+
+    Ok(())
+}
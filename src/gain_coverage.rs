@@ -0,0 +1,153 @@
+use std::collections::HashMap;
+
+use crate::{audit, print_msg_and_wait_for_key};
+
+use autd3::{core::link::Link, prelude::*};
+
+fn check_gain<L: Link + 'static>(
+    autd: &mut Controller<L, firmware::V12_1>,
+    gain: impl Fn() -> BoxedGain,
+    msg: &str,
+) -> anyhow::Result<()> {
+    [Segment::S0, Segment::S1]
+        .into_iter()
+        .try_for_each(|segment| -> anyhow::Result<()> {
+            let expect = autd.inspect(gain())?;
+            autd.send(WithSegment {
+                inner: gain(),
+                segment,
+                transition_mode: Some(TransitionMode::Immediate),
+            })?;
+            match audit::emulators(autd) {
+                Some(emulators) => emulators
+                    .iter()
+                    .zip(expect.iter())
+                    .for_each(|(cpu, expect)| {
+                        assert!(expect.is_some());
+                        assert_eq!(
+                            expect.as_ref().unwrap().data,
+                            cpu.fpga().drives_at(segment, 0)
+                        );
+                    }),
+                None => print_msg_and_wait_for_key(&format!("{:?}: {}", segment, msg)),
+            }
+            std::thread::sleep(std::time::Duration::from_millis(100));
+            autd.fpga_state()?.iter().for_each(|state| {
+                assert!(state.is_some());
+                let state = state.unwrap();
+                assert_eq!(Segment::S0, state.current_mod_segment());
+                assert_eq!(Some(segment), state.current_gain_segment());
+                assert_eq!(None, state.current_stm_segment());
+            });
+            Ok(())
+        })
+}
+
+pub fn gain_coverage_test<L: Link + 'static>(
+    autd: &mut Controller<L, firmware::V12_1>,
+) -> anyhow::Result<()> {
+    autd.send(Sine::new(150. * Hz, Default::default()))?;
+
+    let base = autd.geometry().center();
+    let center = base + 150. * Vector3::z();
+
+    check_gain(
+        autd,
+        || BoxedGain::new(Focus::new(center, Default::default())),
+        "各デバイスの中心から150mm直上に焦点が生成されていること",
+    )?;
+
+    check_gain(
+        autd,
+        || {
+            BoxedGain::new(Focus::new(
+                center,
+                FocusOption {
+                    intensity: Intensity(0x80),
+                    phase_offset: Phase(0x40),
+                },
+            ))
+        },
+        "各デバイスの中心から150mm直上に焦点が生成され, 音圧が小さくなっていること",
+    )?;
+
+    check_gain(
+        autd,
+        || {
+            BoxedGain::new(Bessel::new(
+                base,
+                Vector3::z_axis(),
+                18. * deg,
+                BesselOption {
+                    intensity: Intensity::MAX,
+                    phase_offset: Phase(0x80),
+                },
+            ))
+        },
+        "各デバイスの中心の直上にベッセルビームが生成されていること",
+    )?;
+
+    check_gain(
+        autd,
+        || {
+            BoxedGain::new(Plane::new(
+                Vector3::z_axis(),
+                PlaneOption {
+                    intensity: Intensity::MAX,
+                    phase_offset: Phase::ZERO,
+                },
+            ))
+        },
+        "直上に平面波が出力されていること",
+    )?;
+
+    check_gain(
+        autd,
+        || BoxedGain::new(Uniform::new(Intensity(0x80), Phase(0x40))),
+        "全振動子から一様に出力されていること",
+    )?;
+
+    check_gain(autd, || BoxedGain::new(Null), "出力が止まっていること")?;
+
+    check_gain(
+        autd,
+        || {
+            BoxedGain::new(autd3::gain::Custom::new(|dev| {
+                let dev_idx = dev.idx();
+                move |tr| Drive {
+                    phase: Phase((tr.idx() + dev_idx) as u8),
+                    intensity: Intensity(tr.idx() as u8),
+                }
+            }))
+        },
+        "振動子の番号が大きいほど出力が大きくなっていること",
+    )?;
+
+    check_gain(
+        autd,
+        || {
+            BoxedGain::new(autd3::gain::Group::new(
+                |dev| {
+                    let dev_center = dev.center().x;
+                    move |tr| {
+                        if tr.position().x < dev_center {
+                            Some("focus")
+                        } else {
+                            Some("null")
+                        }
+                    }
+                },
+                HashMap::from([
+                    (
+                        "focus",
+                        BoxedGain::new(Focus::new(center, Default::default())),
+                    ),
+                    ("null", BoxedGain::new(Null)),
+                ]),
+            ))
+        },
+        "各デバイスの左半分だけが出力していること",
+    )?;
+
+    Ok(())
+}
@@ -0,0 +1,140 @@
+use std::sync::Arc;
+
+use crate::{audit, print_msg_and_wait_for_key};
+
+use autd3::{
+    core::{acoustics::propagate, link::Link},
+    prelude::*,
+};
+use autd3_gain_holo::{
+    Amplitude, Complex, GS, GSPAT, Greedy, LM, Naive, NalgebraBackend, Pa, Sphere,
+};
+
+fn field_amplitude(geometry: &Geometry, drives: &[Vec<Drive>], wavenumber: f32, p: &Point3) -> f32 {
+    geometry
+        .iter()
+        .zip(drives.iter())
+        .flat_map(|(dev, drives)| {
+            dev.iter().zip(drives.iter()).map(move |(tr, d)| {
+                propagate::<Sphere>(tr, wavenumber, dev.axial_direction(), p)
+                    * Complex::from_polar(d.intensity.0 as f32 / 255., d.phase.radian())
+            })
+        })
+        .sum::<Complex>()
+        .norm()
+}
+
+fn check_holo<L: Link + 'static>(
+    autd: &mut Controller<L, firmware::V12_1>,
+    name: &str,
+    foci: &[Point3],
+    gain: impl Fn(Vec<(Point3, Amplitude)>) -> BoxedGain,
+) -> anyhow::Result<()> {
+    let target = 5e3 * Pa;
+    let targets = || foci.iter().map(|&p| (p, target)).collect::<Vec<_>>();
+    [Segment::S0, Segment::S1]
+        .into_iter()
+        .try_for_each(|segment| -> anyhow::Result<()> {
+            autd.send(WithSegment {
+                inner: gain(targets()),
+                segment,
+                transition_mode: Some(TransitionMode::Immediate),
+            })?;
+
+            let drives = match audit::emulators(autd) {
+                Some(emulators) => emulators
+                    .iter()
+                    .map(|cpu| cpu.fpga().drives_at(segment, 0))
+                    .collect::<Vec<_>>(),
+                None => {
+                    print_msg_and_wait_for_key(&format!(
+                        "{}, {:?}: 次の{}点に焦点が生成されていること\n{}",
+                        name,
+                        segment,
+                        foci.len(),
+                        foci.iter()
+                            .map(|p| format!("({:.1}, {:.1}, {:.1})", p.x, p.y, p.z))
+                            .collect::<Vec<_>>()
+                            .join("\n")
+                    ));
+                    autd.inspect(gain(targets()))?
+                        .iter()
+                        .map(|r| r.as_ref().unwrap().data.clone())
+                        .collect::<Vec<_>>()
+                }
+            };
+            let wavenumber = autd.environment.wavenumber();
+            let amps = foci
+                .iter()
+                .map(|p| field_amplitude(autd.geometry(), &drives, wavenumber, p))
+                .collect::<Vec<_>>();
+            amps.iter().for_each(|&amp| {
+                assert!(
+                    (amp - target.pascal()).abs() <= target.pascal() * 0.25,
+                    "{}, {:?}: focal amplitudes {:?} are far from the target {:?}",
+                    name,
+                    segment,
+                    amps,
+                    target.pascal()
+                );
+            });
+
+            std::thread::sleep(std::time::Duration::from_millis(100));
+            autd.fpga_state()?.iter().for_each(|state| {
+                assert!(state.is_some());
+                let state = state.unwrap();
+                assert_eq!(Segment::S0, state.current_mod_segment());
+                assert_eq!(Some(segment), state.current_gain_segment());
+                assert_eq!(None, state.current_stm_segment());
+            });
+            Ok(())
+        })
+}
+
+pub fn holo_test<L: Link + 'static>(
+    autd: &mut Controller<L, firmware::V12_1>,
+) -> anyhow::Result<()> {
+    autd.send(Sine::new(150. * Hz, Default::default()))?;
+
+    let center = autd.geometry().center() + Vector3::new(0., 0., 150.0 * mm);
+    let backend = Arc::new(NalgebraBackend::default());
+
+    [
+        vec![
+            center + Vector3::new(-30., 0., 0.),
+            center + Vector3::new(30., 0., 0.),
+        ],
+        vec![
+            center + Vector3::new(-40., -20., 0.),
+            center + Vector3::new(40., -20., 0.),
+            center + Vector3::new(0., 30., 10.),
+        ],
+        vec![
+            center + Vector3::new(-50., -30., 0.),
+            center + Vector3::new(50., -30., -10.),
+            center + Vector3::new(50., 30., 0.),
+            center + Vector3::new(-50., 30., 10.),
+        ],
+    ]
+    .iter()
+    .try_for_each(|foci| -> anyhow::Result<()> {
+        check_holo(autd, "GS", foci, |targets| {
+            BoxedGain::new(GS::new(targets, Default::default(), backend.clone()))
+        })?;
+        check_holo(autd, "GSPAT", foci, |targets| {
+            BoxedGain::new(GSPAT::new(targets, Default::default(), backend.clone()))
+        })?;
+        check_holo(autd, "Naive", foci, |targets| {
+            BoxedGain::new(Naive::new(targets, Default::default(), backend.clone()))
+        })?;
+        check_holo(autd, "LM", foci, |targets| {
+            BoxedGain::new(LM::new(targets, Default::default(), backend.clone()))
+        })?;
+        check_holo(autd, "Greedy", foci, |targets| {
+            BoxedGain::new(Greedy::new(targets, Default::default()))
+        })?;
+        Ok(())
+    })?;
+
+    Ok(())
+}
@@ -0,0 +1,111 @@
+use colored::*;
+
+use crate::{audit, read_input};
+
+use autd3::{core::link::Link, prelude::*};
+
+fn rows(dev: &autd3::core::geometry::Device) -> Vec<Vec<usize>> {
+    dev.iter().fold(Vec::<Vec<usize>>::new(), |mut rows, tr| {
+        match rows.last_mut() {
+            Some(row) if (dev[row[0]].position().y - tr.position().y).abs() < 1e-3 => {
+                row.push(tr.idx())
+            }
+            _ => rows.push(vec![tr.idx()]),
+        }
+        rows
+    })
+}
+
+pub fn layout_walk_test<L: Link + 'static>(
+    autd: &mut Controller<L, firmware::V12_1>,
+) -> anyhow::Result<()> {
+    let dev_idx = read_input(&format!(
+        "デバイスを選択 (0-{})",
+        autd.geometry().num_devices() - 1
+    ))?
+    .parse::<usize>()
+    .unwrap_or(0)
+    .min(autd.geometry().num_devices() - 1);
+    let by_row = read_input("[0]: 1つずつ, [1]: 行ごと")? == "1";
+
+    let groups = if by_row {
+        rows(&autd.geometry()[dev_idx])
+    } else {
+        autd.geometry()[dev_idx]
+            .iter()
+            .map(|tr| vec![tr.idx()])
+            .collect()
+    };
+
+    autd.send((
+        Sine::new(150. * Hz, Default::default()),
+        Silencer::default(),
+    ))?;
+
+    let mut flagged = Vec::new();
+    for group in groups.iter() {
+        let targets = group.clone();
+        autd.send(autd3::gain::Custom::new(move |dev| {
+            let targets = targets.clone();
+            let is_target_dev = dev.idx() == dev_idx;
+            move |tr| {
+                if is_target_dev && targets.contains(&tr.idx()) {
+                    Drive {
+                        phase: Phase::ZERO,
+                        intensity: Intensity::MAX,
+                    }
+                } else {
+                    Drive::NULL
+                }
+            }
+        }))?;
+
+        match audit::emulators(autd) {
+            Some(emulators) => emulators.iter().for_each(|cpu| {
+                cpu.fpga()
+                    .drives_at(Segment::S0, 0)
+                    .iter()
+                    .enumerate()
+                    .for_each(|(tr_idx, d)| {
+                        assert_eq!(
+                            cpu.idx() == dev_idx && group.contains(&tr_idx),
+                            d.intensity == Intensity::MAX
+                        );
+                    });
+            }),
+            None => {
+                group.iter().for_each(|&tr_idx| {
+                    let p = autd.geometry()[dev_idx][tr_idx].position();
+                    println!(
+                        "{}: 振動子[{}] ({:.2}, {:.2}, {:.2})",
+                        "Check".yellow().bold(),
+                        tr_idx,
+                        p.x,
+                        p.y,
+                        p.z
+                    );
+                });
+                match read_input("点灯位置が正しければEnter, 誤っていればn, 中断はq")?.as_str()
+                {
+                    "q" => break,
+                    "n" => flagged.push(group.clone()),
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    autd.send(Null)?;
+
+    flagged.iter().for_each(|group| {
+        println!("{}: {:?}", "NG".red().bold(), group);
+    });
+    assert!(
+        flagged.is_empty(),
+        "{} group(s) of device {} were flagged",
+        flagged.len(),
+        dev_idx
+    );
+
+    Ok(())
+}
@@ -1,24 +1,51 @@
+mod audit;
 mod clear;
 mod debug;
+mod drive_round_trip;
 mod err;
+mod finite_loop;
 mod force_fan;
 mod gain;
+mod gain_coverage;
+mod holo;
+mod layout_walk;
+mod mod_buf_size;
+mod mod_stm_alignment;
 mod modulation;
+mod modulation_coverage;
+mod modulation_file;
 mod output_mask;
+mod partial_device;
 mod phase_corr;
 mod pulse_width_encoder;
+mod sampling_sweep;
 mod silencer;
+mod silencer_boundary;
+mod silencer_non_strict;
+mod silencer_pwe;
+mod silencer_update_rate;
+mod stm_foci_coverage;
+mod stm_foci_limit;
 mod stm_focus;
 mod stm_gain;
+mod stm_gain_mode;
+mod stm_trajectory;
+mod target;
+mod trajectory;
 mod transition;
+mod transition_gpio;
+mod transition_lead;
+mod transition_matrix;
+mod transition_sync;
 
 use colored::*;
 use std::io::{self, Write};
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 
 use autd3::{core::link::Link, driver::firmware::version::FirmwareVersion, prelude::*};
 use autd3_link_soem::{SOEM, Status};
+use autd3_link_twincat::{RemoteTwinCAT, RemoteTwinCATOption};
 
 fn print_check(msg: &str) {
     println!("{}: {}", "Check".yellow().bold(), msg);
@@ -33,9 +60,60 @@ fn print_msg_and_wait_for_key(msg: &str) {
     std::io::stdin().read_line(&mut String::new()).unwrap();
 }
 
-fn run<L: Link>(link: L) -> Result<()> {
-    let mut autd =
-        Controller::<_, firmware::V12_1>::open_with([AUTD3::default(), AUTD3::default()], link)?;
+fn assert_no_mismatches(mismatches: &[String], test: &str) {
+    mismatches.iter().for_each(|m| println!("{}", m));
+    assert!(
+        mismatches.is_empty(),
+        "{} mismatches in {}",
+        mismatches.len(),
+        test
+    );
+}
+
+fn read_input(msg: &str) -> Result<String> {
+    print!("{}: ", msg.green().bold());
+    io::stdout().flush()?;
+    let mut s = String::new();
+    io::stdin().read_line(&mut s)?;
+    Ok(s.trim().to_owned())
+}
+
+fn remote_twincat() -> Result<(RemoteTwinCAT, SenderOption)> {
+    let server_ams_net_id = read_input("サーバーのAMS Net ID (例: 172.16.99.111.1.1)")?;
+    let server_ip = read_input("サーバーのIPアドレス (空欄の場合はAMS Net IDから推定)")?;
+    let client_ams_net_id = read_input("クライアントのAMS Net ID (空欄可)")?;
+    let timeout = read_input("タイムアウト[ms] (空欄の場合はデフォルト)")?;
+    let timeout = if timeout.is_empty() {
+        None
+    } else {
+        Some(std::time::Duration::from_millis(
+            timeout
+                .parse()
+                .with_context(|| format!("タイムアウト[ms]の値が不正です: {:?}", timeout))?,
+        ))
+    };
+    Ok((
+        RemoteTwinCAT::new(
+            server_ams_net_id,
+            RemoteTwinCATOption {
+                server_ip,
+                client_ams_net_id,
+            },
+        ),
+        SenderOption {
+            timeout,
+            ..Default::default()
+        },
+    ))
+}
+
+fn run<L: Link + 'static>(link: L, option: SenderOption) -> Result<()> {
+    let mut autd = Controller::<_, firmware::V12_1>::open_with_option(
+        [AUTD3::default(), AUTD3::default()],
+        link,
+        option,
+        FixedSchedule::default(),
+    )?;
 
     autd.send(GPIOOutputs::new(|_dev, gpio| match gpio {
         GPIOOut::O0 => Some(GPIOOutputType::BaseSignal),
@@ -102,6 +180,74 @@ fn run<L: Link>(link: L) -> Result<()> {
         ("Output Maskテスト", |autd| {
             output_mask::output_mask_test(autd)
         }),
+        ("Gain網羅テスト", |autd| {
+            gain_coverage::gain_coverage_test(autd)
+        }),
+        ("Holoテスト", |autd| holo::holo_test(autd)),
+        ("Segment/Transitionマトリクステスト", |autd| {
+            transition_matrix::transition_matrix_test(autd)
+        }),
+        ("Intensity/Phase網羅テスト", |autd| {
+            drive_round_trip::drive_round_trip_test(autd)
+        }),
+        ("振動子配置確認", |autd| {
+            layout_walk::layout_walk_test(autd)
+        }),
+        ("部分デバイス送信テスト", |autd| {
+            partial_device::partial_device_test(autd)
+        }),
+        ("Modulation網羅テスト", |autd| {
+            modulation_coverage::modulation_coverage_test(autd)
+        }),
+        ("Modulationバッファサイズ境界テスト", |autd| {
+            mod_buf_size::mod_buf_size_test(autd)
+        }),
+        ("サンプリング設定スイープテスト", |autd| {
+            sampling_sweep::sampling_sweep_test(autd)
+        }),
+        ("有限ループ回数テスト", |autd| {
+            finite_loop::finite_loop_test(autd)
+        }),
+        ("Modulation/STMインデックス同期テスト", |autd| {
+            mod_stm_alignment::mod_stm_alignment_test(autd)
+        }),
+        ("ファイルModulationテスト", |autd| {
+            modulation_file::modulation_file_test(autd)
+        }),
+        ("FociSTM多焦点網羅テスト", |autd| {
+            stm_foci_coverage::stm_foci_coverage_test(autd)
+        }),
+        ("FociSTM範囲/サイズ制限テスト", |autd| {
+            stm_foci_limit::stm_foci_limit_test(autd)
+        }),
+        ("GainSTMモード/サイズ制限テスト", |autd| {
+            stm_gain_mode::stm_gain_mode_test(autd)
+        }),
+        ("軌道ライブラリSTMテスト", |autd| {
+            stm_trajectory::stm_trajectory_test(autd)
+        }),
+        ("Silencer(FixedUpdateRate)テスト", |autd| {
+            silencer_update_rate::silencer_update_rate_test(autd)
+        }),
+        ("Silencer非strictモードテスト", |autd| {
+            silencer_non_strict::silencer_non_strict_test(autd)
+        }),
+        (
+            "Silencer/サンプリング設定境界探索テスト",
+            |autd| silencer_boundary::silencer_boundary_test(autd),
+        ),
+        ("Silencer/PWE組み合わせテスト", |autd| {
+            silencer_pwe::silencer_pwe_test(autd)
+        }),
+        ("予約遷移のデバイス間同時性テスト", |autd| {
+            transition_sync::transition_sync_test(autd)
+        }),
+        ("GPIO入力遷移テスト", |autd| {
+            transition_gpio::transition_gpio_test(autd)
+        }),
+        ("遷移時刻リードタイム測定", |autd| {
+            transition_lead::transition_lead_test(autd)
+        }),
     ];
 
     loop {
@@ -121,7 +267,7 @@ fn run<L: Link>(link: L) -> Result<()> {
             _ => break,
         }
 
-        autd.send((Null::default(), Silencer::default()))?;
+        autd.send((Null, Silencer::default()))?;
 
         clear::clear_test(&mut autd)?;
     }
@@ -141,7 +287,7 @@ fn main() -> Result<()> {
     print_check("各デバイスのGPIO[0]ピンとGPIO[1]ピンにオシロスコープを接続していること");
     print_check("各デバイスのGPIOピンに出力がないこと");
 
-    let links = vec!["SOEM", "TwinCAT", "Simulator", "Audit"];
+    let links = ["SOEM", "TwinCAT", "Simulator", "Audit", "RemoteTwinCAT"];
     links.iter().enumerate().for_each(|(i, link)| {
         println!("[{}]: {}", i, link);
     });
@@ -150,23 +296,32 @@ fn main() -> Result<()> {
     let mut s = String::new();
     io::stdin().read_line(&mut s)?;
     match s.trim().parse::<usize>().unwrap_or(0) {
-        1 => run(autd3_link_twincat::TwinCAT::new()?),
-        2 => run(autd3_link_simulator::Simulator::new(
-            "127.0.0.1:8080".parse()?,
-        )),
+        1 => run(autd3_link_twincat::TwinCAT::new()?, Default::default()),
+        2 => run(
+            autd3_link_simulator::Simulator::new("127.0.0.1:8080".parse()?),
+            Default::default(),
+        ),
         3 => run(
             autd3::link::Audit::<autd3::link::audit::version::V12_1>::new(
                 autd3::link::AuditOption::default(),
             ),
+            Default::default(),
         ),
-        _ => run(SOEM::new(
-            |slave, status| {
-                eprintln!("slave[{}]: {}", slave, status);
-                if status == Status::Lost {
-                    std::process::exit(-1);
-                }
-            },
+        4 => {
+            let (link, option) = remote_twincat()?;
+            run(link, option)
+        }
+        _ => run(
+            SOEM::new(
+                |slave, status| {
+                    eprintln!("slave[{}]: {}", slave, status);
+                    if status == Status::Lost {
+                        std::process::exit(-1);
+                    }
+                },
+                Default::default(),
+            ),
             Default::default(),
-        )),
+        ),
     }
 }
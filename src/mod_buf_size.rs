@@ -0,0 +1,180 @@
+use std::num::NonZeroU16;
+
+use crate::{assert_no_mismatches, audit};
+
+use autd3::{
+    core::{common::MOD_BUF_SIZE_MIN, link::Link},
+    driver::firmware::{driver::Driver, v12_1::fpga::MOD_BUF_SIZE_MAX},
+    prelude::*,
+};
+
+// The first modulation frame carries at most 254 samples and the following ones 618
+const SIZES: [usize; 13] = [
+    0,
+    MOD_BUF_SIZE_MIN - 1,
+    MOD_BUF_SIZE_MIN,
+    MOD_BUF_SIZE_MIN + 1,
+    253,
+    254,
+    255,
+    254 + 618 - 1,
+    254 + 618,
+    254 + 618 + 1,
+    MOD_BUF_SIZE_MAX - 1,
+    MOD_BUF_SIZE_MAX,
+    MOD_BUF_SIZE_MAX + 1,
+];
+
+fn buffer(size: usize) -> Vec<u8> {
+    (0..size).map(|i| ((i + size) % 251) as u8).collect()
+}
+
+// Known defect: the firmware does not reset the modulation write page on MODULATION_FLAG_BEGIN, so a
+// modulation written after one that ended on the second 32768-sample page starts on the wrong page.
+// Neither Clear nor a short modulation resets the page, but writing MOD_BUF_SIZE_MAX samples wraps it
+// back to the first page, so every case starts from there.
+fn reset_write_page<L: Link>(
+    autd: &mut Controller<L, firmware::V12_1>,
+    segment: Segment,
+) -> anyhow::Result<()> {
+    autd.send(WithSegment {
+        inner: autd3::modulation::Custom {
+            buffer: buffer(MOD_BUF_SIZE_MAX),
+            sampling_config: SamplingConfig::FREQ_4K,
+        },
+        segment,
+        transition_mode: None,
+    })?;
+    Ok(())
+}
+
+fn check_size<L: Link + 'static>(
+    autd: &mut Controller<L, firmware::V12_1>,
+    loop_behavior: LoopBehavior,
+    segment: Segment,
+    size: usize,
+) -> anyhow::Result<Vec<String>> {
+    let mut mismatches = Vec::new();
+    let mut check = |name: &str, expect: String, actual: String| {
+        if expect != actual {
+            mismatches.push(format!(
+                "{:?}, {:?}, size {}: {} expected {}, actual {}",
+                loop_behavior, segment, size, name, expect, actual
+            ));
+        }
+    };
+
+    let current = autd.fpga_state()?[0].unwrap().current_mod_segment();
+
+    // Finite loops cannot be started immediately, so they are only written
+    let transition_mode =
+        (loop_behavior == LoopBehavior::Infinite).then_some(TransitionMode::Immediate);
+    let res = autd.send(WithLoopBehavior {
+        inner: autd3::modulation::Custom {
+            buffer: buffer(size),
+            sampling_config: SamplingConfig::FREQ_4K,
+        },
+        loop_behavior,
+        segment,
+        transition_mode,
+    });
+
+    let expect_segment = if (MOD_BUF_SIZE_MIN..=MOD_BUF_SIZE_MAX).contains(&size) {
+        check(
+            "result",
+            format!("{:?}", Ok::<_, AUTDDriverError>(())),
+            format!("{:?}", res),
+        );
+        if let Some(emulators) = audit::emulators(autd) {
+            emulators.iter().for_each(|cpu| {
+                check(
+                    &format!("device {} cycle", cpu.idx()),
+                    size.to_string(),
+                    cpu.fpga().modulation_cycle(segment).to_string(),
+                );
+                let actual = cpu.fpga().modulation_buffer(segment);
+                check(
+                    &format!("device {} first mismatched sample", cpu.idx()),
+                    "None".to_string(),
+                    format!(
+                        "{:?}",
+                        buffer(size)
+                            .iter()
+                            .zip(actual.iter())
+                            .position(|(a, b)| a != b)
+                    ),
+                );
+                check(
+                    &format!("device {} loop behavior", cpu.idx()),
+                    format!("{:?}", loop_behavior),
+                    format!("{:?}", cpu.fpga().modulation_loop_behavior(segment)),
+                );
+            });
+        }
+        transition_mode.map_or(current, |_| segment)
+    } else {
+        check(
+            "result",
+            format!(
+                "{:?}",
+                Err::<(), _>(AUTDDriverError::ModulationSizeOutOfRange(
+                    size,
+                    firmware::V12_1.firmware_limits()
+                ))
+            ),
+            format!("{:?}", res),
+        );
+        current
+    };
+
+    std::thread::sleep(std::time::Duration::from_millis(100));
+    autd.fpga_state()?.iter().for_each(|state| {
+        assert!(state.is_some());
+        check(
+            "current segment",
+            format!("{:?}", expect_segment),
+            format!("{:?}", state.unwrap().current_mod_segment()),
+        );
+    });
+
+    Ok(mismatches)
+}
+
+pub fn mod_buf_size_test<L: Link + 'static>(
+    autd: &mut Controller<L, firmware::V12_1>,
+) -> anyhow::Result<()> {
+    let mut mismatches = Vec::new();
+    for loop_behavior in [
+        LoopBehavior::Infinite,
+        LoopBehavior::ONCE,
+        LoopBehavior::Finite(NonZeroU16::new(3).unwrap()),
+    ] {
+        for segment in [Segment::S0, Segment::S1] {
+            for size in SIZES {
+                reset_write_page(autd, segment)?;
+                mismatches.extend(check_size(autd, loop_behavior, segment, size)?);
+            }
+        }
+    }
+
+    // Known defect (see reset_write_page): a modulation written right after one that ended on the
+    // second page is corrupted. Expected to fail until the firmware resets the page on BEGIN.
+    reset_write_page(autd, Segment::S0)?;
+    check_size(
+        autd,
+        LoopBehavior::Infinite,
+        Segment::S0,
+        MOD_BUF_SIZE_MAX - 1,
+    )?;
+    mismatches.extend(
+        check_size(autd, LoopBehavior::Infinite, Segment::S0, MOD_BUF_SIZE_MIN)?
+            .into_iter()
+            .map(|m| format!("known defect, write page not reset on BEGIN: {}", m)),
+    );
+
+    assert_no_mismatches(&mismatches, "modulation buffer size boundary test");
+
+    autd.send(Clear::new())?;
+
+    Ok(())
+}
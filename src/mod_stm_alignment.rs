@@ -0,0 +1,190 @@
+use std::{num::NonZeroU16, time::Duration};
+
+use crate::{assert_no_mismatches, audit, print_msg_and_wait_for_key, trajectory::Trajectory};
+
+use autd3::{
+    core::{common::ULTRASOUND_PERIOD, link::Link},
+    prelude::*,
+};
+
+#[derive(Debug, Clone, Copy)]
+struct Case {
+    mod_divide: u16,
+    mod_cycle: usize,
+    stm_divide: u16,
+    stm_cycle: usize,
+}
+
+impl Case {
+    const fn new(mod_divide: u16, mod_cycle: usize, stm_divide: u16, stm_cycle: usize) -> Self {
+        Self {
+            mod_divide,
+            mod_cycle,
+            stm_divide,
+            stm_cycle,
+        }
+    }
+
+    fn lap(&self) -> Duration {
+        ULTRASOUND_PERIOD * self.mod_divide as u32 * self.mod_cycle as u32
+    }
+
+    // Both indices must cover the same position in the loop
+    fn is_aligned(&self, mod_idx: usize, stm_idx: usize) -> bool {
+        let mod_range =
+            mod_idx * self.mod_divide as usize..(mod_idx + 1) * self.mod_divide as usize;
+        let stm_range =
+            stm_idx * self.stm_divide as usize..(stm_idx + 1) * self.stm_divide as usize;
+        mod_range.start < stm_range.end && stm_range.start < mod_range.end
+    }
+}
+
+const CASES: [Case; 4] = [
+    Case::new(10, 20, 10, 20),
+    Case::new(40, 20, 10, 80),
+    Case::new(10, 80, 40, 20),
+    Case::new(30, 8, 12, 20),
+];
+
+fn send<L: Link>(
+    autd: &mut Controller<L, firmware::V12_1>,
+    case: Case,
+    segment: Segment,
+    loop_behavior: LoopBehavior,
+    transition_mode: Option<TransitionMode>,
+) -> anyhow::Result<()> {
+    let center = autd.geometry().center() + Vector3::new(0., 0., 150.0 * mm);
+    autd.send((
+        WithLoopBehavior {
+            inner: autd3::modulation::Custom {
+                buffer: (0..case.mod_cycle)
+                    .map(|i| (i * 0xFF / (case.mod_cycle - 1)) as u8)
+                    .collect(),
+                sampling_config: SamplingConfig::new(NonZeroU16::new(case.mod_divide).unwrap()),
+            },
+            loop_behavior,
+            segment,
+            transition_mode,
+        },
+        WithLoopBehavior {
+            inner: FociSTM::new(
+                Trajectory::circle(center, 30.0 * mm).points(case.stm_cycle),
+                SamplingConfig::new(NonZeroU16::new(case.stm_divide).unwrap()),
+            ),
+            loop_behavior,
+            segment,
+            transition_mode,
+        },
+    ))?;
+    Ok(())
+}
+
+fn check_alignment<L: Link + 'static>(
+    autd: &mut Controller<L, firmware::V12_1>,
+    case: Case,
+    segment: Segment,
+    clock: &mut DcSysTime,
+    msg: &str,
+) -> anyhow::Result<Vec<String>> {
+    let mut mismatches = Vec::new();
+    // Simulated time must not go backwards, or pending transitions are never taken
+    let start = if DcSysTime::now().sys_time() > clock.sys_time() {
+        DcSysTime::now()
+    } else {
+        *clock
+    } + Duration::from_millis(100);
+    let lap = case.lap();
+    match audit::emulators_mut(autd) {
+        Some(emulators) => emulators.iter_mut().for_each(|cpu| {
+            (0..3 * 7).for_each(|k| {
+                let t = start + lap * k / 7;
+                *clock = t;
+                cpu.update_with_sys_time(t);
+                let fpga = cpu.fpga();
+                let (mod_segment, mod_idx) = (fpga.current_mod_segment(), fpga.current_mod_idx());
+                let (stm_segment, stm_idx) = (fpga.current_stm_segment(), fpga.current_stm_idx());
+                if mod_segment != segment
+                    || stm_segment != segment
+                    || !case.is_aligned(mod_idx, stm_idx)
+                {
+                    mismatches.push(format!(
+                        "{:?}: device {} at {:?} expected {:?}, actual modulation {:?}/{}, STM {:?}/{}",
+                        case,
+                        cpu.idx(),
+                        lap * k / 7,
+                        segment,
+                        mod_segment,
+                        mod_idx,
+                        stm_segment,
+                        stm_idx
+                    ));
+                }
+            });
+        }),
+        None => print_msg_and_wait_for_key(msg),
+    }
+
+    std::thread::sleep(Duration::from_millis(100));
+    autd.fpga_state()?.iter().for_each(|state| {
+        assert!(state.is_some());
+        let state = state.unwrap();
+        assert_eq!(segment, state.current_mod_segment());
+        assert_eq!(Some(segment), state.current_stm_segment());
+    });
+
+    Ok(mismatches)
+}
+
+pub fn mod_stm_alignment_test<L: Link + 'static>(
+    autd: &mut Controller<L, firmware::V12_1>,
+) -> anyhow::Result<()> {
+    autd.send(Silencer::disable())?;
+    autd.send(GPIOOutputs::new(|_dev, gpio| match gpio {
+        GPIOOut::O0 => Some(GPIOOutputType::ModIdx(0)),
+        GPIOOut::O1 => Some(GPIOOutputType::StmIdx(0)),
+        _ => None,
+    }))?;
+
+    let mut clock = DcSysTime::now();
+    let mut mismatches = Vec::new();
+    for case in CASES {
+        let msg = format!(
+            "各デバイスのGPIO[0]とGPIO[1]のパルスが{:?}周期で同期していること",
+            case.lap()
+        );
+
+        send(
+            autd,
+            case,
+            Segment::S0,
+            LoopBehavior::Infinite,
+            Some(TransitionMode::Immediate),
+        )?;
+        mismatches.extend(check_alignment(autd, case, Segment::S0, &mut clock, &msg)?);
+
+        for segment in [Segment::S1, Segment::S0] {
+            send(
+                autd,
+                case,
+                segment,
+                LoopBehavior::Finite(NonZeroU16::MAX),
+                None,
+            )?;
+            autd.send((
+                SwapSegment::Modulation(segment, TransitionMode::SyncIdx),
+                SwapSegment::FociSTM(segment, TransitionMode::SyncIdx),
+            ))?;
+            mismatches.extend(check_alignment(autd, case, segment, &mut clock, &msg)?);
+        }
+    }
+
+    assert_no_mismatches(&mismatches, "modulation/STM alignment test");
+
+    autd.send(GPIOOutputs::new(|_dev, gpio| match gpio {
+        GPIOOut::O0 => Some(GPIOOutputType::BaseSignal),
+        _ => None,
+    }))?;
+    autd.send(Clear::new())?;
+
+    Ok(())
+}
@@ -84,10 +84,12 @@ pub fn modulation_test<L: Link>(autd: &mut Controller<L, firmware::V12_1>) -> an
 
     autd.send((
         autd3::modulation::Custom {
-            buffer: std::iter::repeat([vec![0xFF; 1], vec![0; MOD_BUF_SIZE_MAX / 2 - 1]].concat())
-                .take(2)
-                .flatten()
-                .collect(),
+            buffer: std::iter::repeat_n(
+                [vec![0xFF; 1], vec![0; MOD_BUF_SIZE_MAX / 2 - 1]].concat(),
+                2,
+            )
+            .flatten()
+            .collect(),
             sampling_config: SamplingConfig::FREQ_4K,
         },
         Focus::new(
@@ -110,10 +112,12 @@ pub fn modulation_test<L: Link>(autd: &mut Controller<L, firmware::V12_1>) -> an
 
     autd.send((
         autd3::modulation::Custom {
-            buffer: std::iter::repeat([vec![0; MOD_BUF_SIZE_MAX / 2 - 1], vec![0xFF; 1]].concat())
-                .take(2)
-                .flatten()
-                .collect(),
+            buffer: std::iter::repeat_n(
+                [vec![0; MOD_BUF_SIZE_MAX / 2 - 1], vec![0xFF; 1]].concat(),
+                2,
+            )
+            .flatten()
+            .collect(),
             sampling_config: SamplingConfig::FREQ_4K,
         },
         Focus::new(
@@ -0,0 +1,204 @@
+use crate::{audit, print_msg_and_wait_for_key};
+
+use autd3::{
+    core::link::Link,
+    modulation::{BoxedModulation, Custom, Fourier},
+    prelude::*,
+};
+
+fn inspect_modulation<L: Link>(
+    autd: &Controller<L, firmware::V12_1>,
+    modulation: BoxedModulation,
+) -> anyhow::Result<(Vec<u8>, SamplingConfig)> {
+    let result = autd.inspect(modulation)?;
+    let result = result.iter().next().unwrap().as_ref().unwrap();
+    Ok((result.data.clone(), result.config))
+}
+
+fn check_modulation<L: Link + 'static>(
+    autd: &mut Controller<L, firmware::V12_1>,
+    modulation: impl Fn() -> BoxedModulation,
+    expect: Option<Vec<u8>>,
+    msg: &str,
+) -> anyhow::Result<()> {
+    let (buffer, config) = inspect_modulation(autd, modulation())?;
+    if let Some(expect) = expect {
+        assert_eq!(expect, buffer);
+    }
+    [Segment::S0, Segment::S1]
+        .into_iter()
+        .try_for_each(|segment| -> anyhow::Result<()> {
+            autd.send(WithSegment {
+                inner: modulation(),
+                segment,
+                transition_mode: Some(TransitionMode::Immediate),
+            })?;
+            match audit::emulators(autd) {
+                Some(emulators) => emulators.iter().for_each(|cpu| {
+                    assert_eq!(buffer, cpu.fpga().modulation_buffer(segment));
+                    assert_eq!(
+                        config.divide().unwrap(),
+                        cpu.fpga().modulation_freq_divide(segment)
+                    );
+                }),
+                None => print_msg_and_wait_for_key(&format!("{:?}: {}", segment, msg)),
+            }
+            std::thread::sleep(std::time::Duration::from_millis(100));
+            autd.fpga_state()?.iter().for_each(|state| {
+                assert!(state.is_some());
+                let state = state.unwrap();
+                assert_eq!(segment, state.current_mod_segment());
+                assert_eq!(Some(Segment::S0), state.current_gain_segment());
+                assert_eq!(None, state.current_stm_segment());
+            });
+            Ok(())
+        })
+}
+
+pub fn modulation_coverage_test<L: Link + 'static>(
+    autd: &mut Controller<L, firmware::V12_1>,
+) -> anyhow::Result<()> {
+    autd.send(Focus::new(
+        autd.geometry().center() + 150. * Vector3::z(),
+        Default::default(),
+    ))?;
+
+    let square = || {
+        Square::new(
+            200. * Hz,
+            SquareOption {
+                low: 0x20,
+                high: 0xE0,
+                duty: 0.25,
+                ..Default::default()
+            },
+        )
+    };
+    let square_buffer = [vec![0xE0; 5], vec![0x20; 15]].concat();
+    check_modulation(
+        autd,
+        || BoxedModulation::new(square()),
+        Some(square_buffer.clone()),
+        "200Hzの矩形波(Duty比25%)のAMが適用されていること",
+    )?;
+
+    check_modulation(
+        autd,
+        || {
+            BoxedModulation::new(Sine::new(
+                150. * Hz,
+                SineOption {
+                    intensity: 0x80,
+                    offset: 0x40,
+                    phase: PI / 2. * rad,
+                    clamp: false,
+                    ..Default::default()
+                },
+            ))
+        },
+        None,
+        "150Hzの弱いAMが適用されていること",
+    )?;
+
+    check_modulation(
+        autd,
+        || {
+            BoxedModulation::new(Sine::new(
+                150. * Hz,
+                SineOption {
+                    intensity: 0xFF,
+                    offset: 0x40,
+                    clamp: true,
+                    ..Default::default()
+                },
+            ))
+        },
+        None,
+        "150HzのAMが適用されていること",
+    )?;
+
+    let fourier = || {
+        Fourier::new(
+            [
+                Sine::new(100. * Hz, Default::default()),
+                Sine::new(
+                    150. * Hz,
+                    SineOption {
+                        phase: PI / 2. * rad,
+                        ..Default::default()
+                    },
+                ),
+                Sine::new(
+                    200. * Hz,
+                    SineOption {
+                        intensity: 0x80,
+                        ..Default::default()
+                    },
+                ),
+            ],
+            Default::default(),
+        )
+    };
+    check_modulation(
+        autd,
+        || BoxedModulation::new(fourier()),
+        None,
+        "100Hz, 150Hz, 200Hzの合成波のAMが適用されていること",
+    )?;
+
+    let (sine_buffer, _) = inspect_modulation(
+        autd,
+        BoxedModulation::new(Sine::new(150. * Hz, Default::default())),
+    )?;
+    check_modulation(
+        autd,
+        || {
+            BoxedModulation::new(RadiationPressure::new(Sine::new(
+                150. * Hz,
+                Default::default(),
+            )))
+        },
+        Some(
+            sine_buffer
+                .iter()
+                .map(|&v| ((v as f32 / 255.).sqrt() * 255.).round() as u8)
+                .collect(),
+        ),
+        "150HzのAMが適用されていること",
+    )?;
+
+    let coef = [0.25, 0.5, 0.25];
+    check_modulation(
+        autd,
+        || BoxedModulation::new(Fir::new(square(), coef)),
+        Some(
+            (0..square_buffer.len())
+                .map(|i| {
+                    (0..coef.len())
+                        .map(|j| {
+                            square_buffer[(i + j + square_buffer.len() - 1) % square_buffer.len()]
+                                as f32
+                                * coef[j]
+                        })
+                        .sum::<f32>() as u8
+                })
+                .collect(),
+        ),
+        "200Hzの矩形波の立ち上がりと立ち下がりが緩やかなAMが適用されていること",
+    )?;
+
+    let (cache, config) = inspect_modulation(autd, BoxedModulation::new(fourier()))?;
+    check_modulation(
+        autd,
+        || {
+            BoxedModulation::new(Custom {
+                buffer: cache.clone(),
+                sampling_config: config,
+            })
+        },
+        Some(cache.clone()),
+        "100Hz, 150Hz, 200Hzの合成波のAMが適用されていること",
+    )?;
+
+    Ok(())
+}
@@ -0,0 +1,146 @@
+use std::path::Path;
+
+use anyhow::Context;
+
+use crate::{audit, print_msg_and_wait_for_key, read_input};
+
+use autd3::{core::link::Link, prelude::*};
+
+fn load_wav(path: &Path, config: SamplingConfig) -> anyhow::Result<Vec<u8>> {
+    let mut reader = hound::WavReader::open(path)?;
+    let spec = reader.spec();
+    if spec.sample_format != hound::SampleFormat::Int || ![8, 16].contains(&spec.bits_per_sample) {
+        anyhow::bail!(
+            "unsupported WAV format: {:?} {}bit",
+            spec.sample_format,
+            spec.bits_per_sample
+        );
+    }
+    let scale = (1 << (spec.bits_per_sample - 1)) as f32;
+    let samples = reader
+        .samples::<i16>()
+        .step_by(spec.channels as usize)
+        .map(|s| s.map(|s| s as f32 / scale))
+        .collect::<Result<Vec<_>, _>>()?;
+    if samples.is_empty() {
+        anyhow::bail!("WAV file has no samples");
+    }
+
+    // Linear interpolation from the file rate to the sampling rate of the modulation
+    let ratio = spec.sample_rate as f32 / config.freq()?.hz();
+    let len = (samples.len() as f32 / ratio).round() as usize;
+    Ok((0..len)
+        .map(|i| {
+            let x = i as f32 * ratio;
+            let idx = x.floor() as usize;
+            let a = samples[idx.min(samples.len() - 1)];
+            let b = samples[(idx + 1).min(samples.len() - 1)];
+            let v = a + (b - a) * (x - idx as f32);
+            ((v + 1.) / 2. * 255.).round().clamp(0., 255.) as u8
+        })
+        .collect())
+}
+
+fn load_csv(path: &Path, column: usize) -> anyhow::Result<Vec<u8>> {
+    std::fs::read_to_string(path)?
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .filter_map(|(i, line)| {
+            let value = line.split(',').nth(column).map(str::trim);
+            match value.map(str::parse::<u8>) {
+                Some(Ok(v)) => Some(Ok(v)),
+                // The first line may be a header
+                Some(Err(_)) if i == 0 => None,
+                _ => Some(Err(anyhow::anyhow!(
+                    "line {}: invalid value in column {}: {:?}",
+                    i + 1,
+                    column,
+                    value
+                ))),
+            }
+        })
+        .collect()
+}
+
+fn load(path: &Path, config: SamplingConfig, column: usize) -> anyhow::Result<Vec<u8>> {
+    match path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_ascii_lowercase())
+        .as_deref()
+    {
+        Some("wav") => load_wav(path, config),
+        Some("csv") => load_csv(path, column),
+        _ => Ok(std::fs::read(path)?),
+    }
+}
+
+pub fn modulation_file_test<L: Link + 'static>(
+    autd: &mut Controller<L, firmware::V12_1>,
+) -> anyhow::Result<()> {
+    let path = read_input("ファイルパス (.wav, .csv, それ以外はu8の生データ)")?;
+    let path = Path::new(&path);
+    let freq = read_input("サンプリング周波数[Hz] (空欄の場合は4000)")?;
+    let freq = if freq.is_empty() {
+        4000.
+    } else {
+        freq.parse::<f32>()
+            .with_context(|| format!("サンプリング周波数[Hz]の値が不正です: {:?}", freq))?
+    };
+    let config = SamplingConfig::new(freq * Hz).into_nearest();
+    let column = if path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("csv"))
+    {
+        let column = read_input("列番号 (空欄の場合は0)")?;
+        if column.is_empty() {
+            0
+        } else {
+            column
+                .parse::<usize>()
+                .with_context(|| format!("列番号の値が不正です: {:?}", column))?
+        }
+    } else {
+        0
+    };
+
+    let buffer = load(path, config, column)?;
+    println!(
+        "{} samples at {:?} ({:?})",
+        buffer.len(),
+        config.freq()?,
+        config.period()? * buffer.len() as u32
+    );
+
+    autd.send((
+        autd3::modulation::Custom {
+            buffer: buffer.clone(),
+            sampling_config: config,
+        },
+        Focus::new(
+            autd.geometry().center() + 150. * Vector3::z(),
+            Default::default(),
+        ),
+    ))?;
+    match audit::emulators(autd) {
+        Some(emulators) => emulators.iter().for_each(|cpu| {
+            assert_eq!(buffer, cpu.fpga().modulation_buffer(Segment::S0));
+            assert_eq!(
+                config.divide().unwrap(),
+                cpu.fpga().modulation_freq_divide(Segment::S0)
+            );
+        }),
+        None => print_msg_and_wait_for_key(&format!("{}のAMが適用されていること", path.display())),
+    }
+    std::thread::sleep(std::time::Duration::from_millis(100));
+    autd.fpga_state()?.iter().for_each(|state| {
+        assert!(state.is_some());
+        let state = state.unwrap();
+        assert_eq!(Segment::S0, state.current_mod_segment());
+        assert_eq!(Some(Segment::S0), state.current_gain_segment());
+        assert_eq!(None, state.current_stm_segment());
+    });
+
+    Ok(())
+}
@@ -0,0 +1,199 @@
+use std::collections::HashMap;
+
+use crate::{audit, print_msg_and_wait_for_key};
+
+use autd3::{
+    core::{datagram::Datagram, geometry::Device, link::Link},
+    driver::datagram::{FixedCompletionSteps, FixedUpdateRate},
+    prelude::*,
+};
+
+#[derive(Debug, PartialEq)]
+struct Snapshot {
+    mod_segment: Segment,
+    stm_segment: Segment,
+    modulation: [(Vec<u8>, u16); 2],
+    drives: [Vec<Drive>; 2],
+    stm: [(usize, u16, bool); 2],
+    silencer: (FixedUpdateRate, FixedCompletionSteps, bool),
+    pulse_width_encoder: Vec<PulseWidth<9, u16>>,
+}
+
+fn snapshots<L: Link + 'static>(autd: &Controller<L, firmware::V12_1>) -> Option<Vec<Snapshot>> {
+    audit::emulators(autd).map(|emulators| {
+        emulators
+            .iter()
+            .map(|cpu| {
+                let fpga = cpu.fpga();
+                Snapshot {
+                    mod_segment: fpga.current_mod_segment(),
+                    stm_segment: fpga.current_stm_segment(),
+                    modulation: [Segment::S0, Segment::S1].map(|segment| {
+                        (
+                            fpga.modulation_buffer(segment),
+                            fpga.modulation_freq_divide(segment),
+                        )
+                    }),
+                    drives: [Segment::S0, Segment::S1].map(|segment| fpga.drives_at(segment, 0)),
+                    stm: [Segment::S0, Segment::S1].map(|segment| {
+                        (
+                            fpga.stm_cycle(segment),
+                            fpga.stm_freq_divide(segment),
+                            fpga.is_stm_gain_mode(segment),
+                        )
+                    }),
+                    silencer: (
+                        fpga.silencer_update_rate(),
+                        fpga.silencer_completion_steps(),
+                        fpga.silencer_fixed_update_rate_mode(),
+                    ),
+                    pulse_width_encoder: fpga.pulse_width_encoder_table(),
+                }
+            })
+            .collect()
+    })
+}
+
+fn only<D: Datagram>(target: usize, datagram: D) -> Group<(), D, impl Fn(&Device) -> Option<()>>
+where
+    AUTDDriverError: From<D::Error>,
+{
+    Group::new(
+        move |dev| (dev.idx() == target).then_some(()),
+        HashMap::from([((), datagram)]),
+    )
+}
+
+fn check_untouched<L: Link + 'static>(
+    autd: &Controller<L, firmware::V12_1>,
+    before: Option<Vec<Snapshot>>,
+    target: usize,
+    msg: &str,
+) {
+    match (before, snapshots(autd)) {
+        (Some(before), Some(after)) => {
+            before
+                .iter()
+                .zip(after.iter())
+                .enumerate()
+                .for_each(|(dev_idx, (before, after))| {
+                    if dev_idx == target {
+                        assert_ne!(before, after, "device {} was not updated", dev_idx);
+                    } else {
+                        assert_eq!(before, after, "device {} was updated", dev_idx);
+                    }
+                })
+        }
+        _ => print_msg_and_wait_for_key(msg),
+    }
+}
+
+fn check_segments<L: Link>(
+    autd: &mut Controller<L, firmware::V12_1>,
+    expect: impl Fn(usize) -> (Segment, Option<Segment>, Option<Segment>),
+) -> anyhow::Result<()> {
+    std::thread::sleep(std::time::Duration::from_millis(100));
+    autd.fpga_state()?
+        .iter()
+        .enumerate()
+        .for_each(|(dev_idx, state)| {
+            assert!(state.is_some());
+            let state = state.unwrap();
+            let (mod_segment, gain_segment, stm_segment) = expect(dev_idx);
+            assert_eq!(mod_segment, state.current_mod_segment());
+            assert_eq!(gain_segment, state.current_gain_segment());
+            assert_eq!(stm_segment, state.current_stm_segment());
+        });
+    Ok(())
+}
+
+pub fn partial_device_test<L: Link + 'static>(
+    autd: &mut Controller<L, firmware::V12_1>,
+) -> anyhow::Result<()> {
+    autd.send((Static::default(), Null))?;
+    autd.send(Silencer::default())?;
+
+    let center = autd.geometry().center() + Vector3::new(0., 0., 150.0 * mm);
+
+    let before = snapshots(autd);
+    autd.send(only(
+        0,
+        WithSegment {
+            inner: Focus::new(center, Default::default()),
+            segment: Segment::S1,
+            transition_mode: Some(TransitionMode::Immediate),
+        },
+    ))?;
+    check_untouched(
+        autd,
+        before,
+        0,
+        "0番目のデバイスのみから超音波が出力されていること",
+    );
+    check_segments(autd, |dev_idx| match dev_idx {
+        0 => (Segment::S0, Some(Segment::S1), None),
+        _ => (Segment::S0, Some(Segment::S0), None),
+    })?;
+
+    let before = snapshots(autd);
+    autd.send(only(
+        1,
+        WithSegment {
+            inner: Sine::new(150. * Hz, Default::default()),
+            segment: Segment::S1,
+            transition_mode: Some(TransitionMode::Immediate),
+        },
+    ))?;
+    check_untouched(autd, before, 1, "0番目のデバイスの出力に変化がないこと");
+    check_segments(autd, |dev_idx| match dev_idx {
+        0 => (Segment::S0, Some(Segment::S1), None),
+        _ => (Segment::S1, Some(Segment::S0), None),
+    })?;
+
+    let before = snapshots(autd);
+    autd.send(only(
+        1,
+        WithSegment {
+            inner: FociSTM::new(
+                (0..2)
+                    .map(|i| center + Vector3::new(30. * (2. * i as f32 - 1.), 0., 0.))
+                    .collect::<Vec<_>>(),
+                1. * Hz,
+            ),
+            segment: Segment::S0,
+            transition_mode: Some(TransitionMode::Immediate),
+        },
+    ))?;
+    check_untouched(
+        autd,
+        before,
+        1,
+        "1番目のデバイスのみから150Hzの振幅変調が掛かった超音波が1Hzで左右に移動しながら出力されていること",
+    );
+    check_segments(autd, |dev_idx| match dev_idx {
+        0 => (Segment::S0, Some(Segment::S1), None),
+        _ => (Segment::S1, None, Some(Segment::S0)),
+    })?;
+
+    let before = snapshots(autd);
+    autd.send(only(0, Silencer::disable()))?;
+    check_untouched(autd, before, 0, "1番目のデバイスの出力に変化がないこと");
+
+    // Group only accepts the v10 pulse width encoder operation, which has no 9-bit table, so a pulse
+    // width encoder cannot be sent to a single device on v12.1
+    println!("PulseWidthEncoder is not coverable via Group on v12.1; skipped");
+
+    let before = snapshots(autd);
+    assert_eq!(
+        Err(AUTDDriverError::UnusedKey("()".to_string())),
+        autd.send(Group::new(
+            |_dev| None,
+            HashMap::from([((), Silencer::disable())]),
+        ))
+    );
+    if let (Some(before), Some(after)) = (before, snapshots(autd)) {
+        assert_eq!(before, after);
+    }
+
+    Ok(())
+}
@@ -1,6 +1,6 @@
 use std::num::NonZeroU16;
 
-use crate::print_msg_and_wait_for_key;
+use crate::{print_msg_and_wait_for_key, trajectory::Trajectory};
 
 use autd3::{
     core::common::{SILENCER_STEPS_INTENSITY_DEFAULT, SILENCER_STEPS_PHASE_DEFAULT},
@@ -55,11 +55,10 @@ pub fn silencer_test<L: Link>(autd: &mut Controller<L, firmware::V12_1>) -> anyh
         let point_num = 10;
         let radius = 30.0 * mm;
         let gen_foci = || {
-            (0..point_num).map(|i| {
-                let theta = 2.0 * PI * i as f32 / point_num as f32;
-                let p = radius * Vector3::new(theta.cos(), theta.sin(), 0.0);
-                ControlPoint::new(center + p, Phase::ZERO)
-            })
+            Trajectory::circle(center, radius)
+                .points(point_num)
+                .into_iter()
+                .map(|p| ControlPoint::new(p, Phase::ZERO))
         };
         let stm = FociSTM::new(gen_foci().collect::<Vec<_>>(), 50. * Hz);
         autd.send(stm)?;
@@ -0,0 +1,103 @@
+use crate::{
+    assert_no_mismatches,
+    target::{self, Target, send_target, silencer},
+};
+
+use autd3::{core::link::Link, prelude::*};
+
+const INTENSITY_STEPS: [u16; 4] = [1, 10, 37, 100];
+const PHASE_STEPS: [u16; 4] = [1, 40, 77, 200];
+// Well above the largest completion steps in the grid
+const DIVIDE_MAX: u16 = 1024;
+
+#[derive(Debug, Clone, Copy)]
+enum Mode {
+    Segment(Segment),
+    Swap,
+}
+
+// Returns whether `divide` is accepted with the given completion steps in strict mode
+fn probe<L: Link>(
+    autd: &mut Controller<L, firmware::V12_1>,
+    target: Target,
+    mode: Mode,
+    (intensity, phase): (u16, u16),
+    divide: u16,
+) -> anyhow::Result<bool> {
+    target::reset(autd)?;
+    let res = match mode {
+        Mode::Segment(segment) => {
+            autd.send(silencer(intensity, phase, true))?;
+            send_target(autd, target, divide, segment)
+        }
+        Mode::Swap => {
+            send_target(autd, target, divide, Segment::S1)?;
+            autd.send(silencer(intensity, phase, true))?;
+            autd.send(target.swap(Segment::S1, TransitionMode::Immediate))
+        }
+    };
+    match res {
+        Ok(()) => Ok(true),
+        Err(AUTDDriverError::InvalidSilencerSettings) => Ok(false),
+        Err(e) => Err(e.into()),
+    }
+}
+
+// Binary search for the smallest accepted divide, assuming that the check is monotonic
+fn search<L: Link>(
+    autd: &mut Controller<L, firmware::V12_1>,
+    target: Target,
+    mode: Mode,
+    steps: (u16, u16),
+) -> anyhow::Result<Option<u16>> {
+    if !probe(autd, target, mode, steps, DIVIDE_MAX)? {
+        return Ok(None);
+    }
+    let (mut lo, mut hi) = (0u16, DIVIDE_MAX);
+    while hi - lo > 1 {
+        let mid = lo + (hi - lo) / 2;
+        if probe(autd, target, mode, steps, mid)? {
+            hi = mid;
+        } else {
+            lo = mid;
+        }
+    }
+    Ok(Some(hi))
+}
+
+pub fn silencer_boundary_test<L: Link>(
+    autd: &mut Controller<L, firmware::V12_1>,
+) -> anyhow::Result<()> {
+    let mut mismatches = Vec::new();
+    for intensity in INTENSITY_STEPS {
+        for phase in PHASE_STEPS {
+            for target in [Target::Modulation, Target::FociSTM, Target::GainSTM] {
+                for mode in [
+                    Mode::Segment(Segment::S0),
+                    Mode::Segment(Segment::S1),
+                    Mode::Swap,
+                ] {
+                    let expect = Some(target.min_divide(intensity, phase));
+                    let actual = search(autd, target, mode, (intensity, phase))?;
+                    println!(
+                        "intensity {:>3}, phase {:>3}, {:?} {:?}: {:?}",
+                        intensity, phase, target, mode, actual
+                    );
+                    if expect != actual {
+                        mismatches.push(format!(
+                            "intensity {}, phase {}, {:?} {:?}: expected smallest divide {:?}, actual {:?}",
+                            intensity, phase, target, mode, expect, actual
+                        ));
+                    }
+                }
+            }
+        }
+    }
+
+    assert_no_mismatches(&mismatches, "silencer/sampling boundary search");
+
+    autd.send((Static::default(), Null::new()))?;
+    autd.send(Silencer::default())?;
+
+    Ok(())
+}
@@ -0,0 +1,214 @@
+use std::num::NonZeroU16;
+
+use crate::{
+    assert_no_mismatches, audit, print_msg_and_wait_for_key,
+    target::{self, Target, send_target, silencer},
+};
+
+use autd3::{
+    core::{common::ULTRASOUND_PERIOD, link::Link},
+    prelude::*,
+};
+
+fn check_acceptance<L: Link>(
+    autd: &mut Controller<L, firmware::V12_1>,
+    strict: bool,
+) -> anyhow::Result<Vec<String>> {
+    let expect = |valid: bool| {
+        if valid || !strict {
+            Ok(())
+        } else {
+            Err(AUTDDriverError::InvalidSilencerSettings)
+        }
+    };
+    let mut mismatches = Vec::new();
+    let mut check = |name: String, expect: Result<(), AUTDDriverError>, actual| {
+        if expect != actual {
+            mismatches.push(format!(
+                "strict: {}, {}: expected {:?}, actual {:?}",
+                strict, name, expect, actual
+            ));
+        }
+    };
+
+    for target in [Target::Modulation, Target::FociSTM, Target::GainSTM] {
+        target::reset(autd)?;
+        autd.send(silencer(10, 40, strict))?;
+        let min = target.min_divide(10, 40);
+        for (divide, segment) in [
+            (min, Segment::S0),
+            (min - 1, Segment::S0),
+            (min - 1, Segment::S1),
+        ] {
+            check(
+                format!("{:?} divide {} on {:?}", target, divide, segment),
+                expect(divide >= min),
+                send_target(autd, target, divide, segment),
+            );
+        }
+
+        target::reset(autd)?;
+        autd.send(silencer(10, 40, strict))?;
+        send_target(autd, target, min, Segment::S1)?;
+        autd.send(silencer(20, 80, strict))?;
+        check(
+            format!("{:?} swap to divide {} with 20/80 steps", target, min),
+            expect(false),
+            autd.send(target.swap(Segment::S1, TransitionMode::Immediate)),
+        );
+    }
+
+    target::reset(autd)?;
+
+    Ok(mismatches)
+}
+
+// Feeds the output of the segment to the silencer of the emulator for `periods` ultrasound periods
+// and returns the range of the output after the first loop
+fn intensity_range<L: Link + 'static>(
+    autd: &Controller<L, firmware::V12_1>,
+    periods: usize,
+) -> Option<Vec<(u8, u8)>> {
+    audit::emulators(autd).map(|emulators| {
+        emulators
+            .iter()
+            .map(|cpu| {
+                let fpga = cpu.fpga();
+                let segment = fpga.current_mod_segment();
+                let divide = fpga.modulation_freq_divide(segment) as usize;
+                let cycle = fpga.modulation_cycle(segment);
+                let mut silencer = fpga.silencer_emulator_intensity(fpga.modulation_at(segment, 0));
+                let output = (0..periods)
+                    .map(|k| silencer.apply(fpga.modulation_at(segment, (k / divide) % cycle)))
+                    .skip(divide * cycle)
+                    .collect::<Vec<_>>();
+                (*output.iter().min().unwrap(), *output.iter().max().unwrap())
+            })
+            .collect()
+    })
+}
+
+fn phase_range<L: Link + 'static>(
+    autd: &Controller<L, firmware::V12_1>,
+    periods: usize,
+) -> Option<Vec<(u8, u8)>> {
+    audit::emulators(autd).map(|emulators| {
+        emulators
+            .iter()
+            .map(|cpu| {
+                let fpga = cpu.fpga();
+                let segment = fpga.current_stm_segment();
+                let divide = fpga.stm_freq_divide(segment) as usize;
+                let cycle = fpga.stm_cycle(segment);
+                let phase = |idx: usize| fpga.drives_at(segment, idx)[0].phase.0;
+                let mut silencer = fpga.silencer_emulator_phase(phase(0));
+                let output = (0..periods)
+                    .map(|k| silencer.apply(phase((k / divide) % cycle)))
+                    .skip(divide * cycle)
+                    .collect::<Vec<_>>();
+                (*output.iter().min().unwrap(), *output.iter().max().unwrap())
+            })
+            .collect()
+    })
+}
+
+// In non-strict mode a change that does not complete within one sample is cut short by the next
+// one, so the output only swings over a part of the input range
+fn expected_swing(full: u8, divide: u16, steps: u16) -> u8 {
+    (full as u32 * divide.min(steps) as u32 / steps as u32) as u8
+}
+
+fn check_swing(
+    name: &str,
+    expect: u8,
+    actual: Option<Vec<(u8, u8)>>,
+    mismatches: &mut Vec<String>,
+) -> bool {
+    match actual {
+        Some(actual) => {
+            actual.iter().enumerate().for_each(|(dev, &(min, max))| {
+                if (max - min).abs_diff(expect) > 1 {
+                    mismatches.push(format!(
+                        "{}: device {} expected swing {}, actual {:?}",
+                        name,
+                        dev,
+                        expect,
+                        (min, max)
+                    ));
+                }
+            });
+            true
+        }
+        None => false,
+    }
+}
+
+pub fn silencer_non_strict_test<L: Link + 'static>(
+    autd: &mut Controller<L, firmware::V12_1>,
+) -> anyhow::Result<()> {
+    let mut mismatches = Vec::new();
+    for strict in [true, false] {
+        mismatches.extend(check_acceptance(autd, strict)?);
+    }
+
+    // Intensity: square wave modulation with 10 completion steps
+    for divide in [10, 5, 2] {
+        autd.send((Static::default(), Uniform::new(Intensity::MAX, Phase::ZERO)))?;
+        autd.send(Silencer::disable())?;
+        autd.send(silencer(10, 40, false))?;
+        autd.send(autd3::modulation::Custom {
+            buffer: vec![0xFF, 0x00],
+            sampling_config: SamplingConfig::new(NonZeroU16::new(divide).unwrap()),
+        })?;
+        let expect = expected_swing(0xFF, divide, 10);
+        let name = format!("intensity, divide {}", divide);
+        if !check_swing(
+            &name,
+            expect,
+            intensity_range(autd, 8 * divide as usize),
+            &mut mismatches,
+        ) {
+            print_msg_and_wait_for_key(&format!(
+                "{:?}周期の矩形波AMが適用され, 振幅が最大の約{}%であること",
+                ULTRASOUND_PERIOD * divide as u32 * 2,
+                expect as u32 * 100 / 0xFF
+            ));
+        }
+    }
+
+    // Phase: GainSTM between two phases with 40 completion steps
+    for divide in [40, 20, 10] {
+        autd.send((Static::default(), Null::new()))?;
+        autd.send(Silencer::disable())?;
+        autd.send(silencer(10, 40, false))?;
+        autd.send(GainSTM::new(
+            vec![
+                Uniform::new(Intensity::MAX, Phase::ZERO),
+                Uniform::new(Intensity::MAX, Phase(0x40)),
+            ],
+            SamplingConfig::new(NonZeroU16::new(divide).unwrap()),
+            Default::default(),
+        ))?;
+        let expect = expected_swing(0x40, divide, 40);
+        let name = format!("phase, divide {}", divide);
+        if !check_swing(
+            &name,
+            expect,
+            phase_range(autd, 8 * divide as usize),
+            &mut mismatches,
+        ) {
+            print_msg_and_wait_for_key(&format!(
+                "{:?}周期で位相が0から約{}まで変化していること",
+                ULTRASOUND_PERIOD * divide as u32 * 2,
+                expect
+            ));
+        }
+    }
+
+    assert_no_mismatches(&mismatches, "non-strict silencer test");
+
+    autd.send(Silencer::disable())?;
+    autd.send(Clear::new())?;
+
+    Ok(())
+}
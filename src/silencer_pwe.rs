@@ -0,0 +1,92 @@
+use anyhow::Context;
+
+use crate::{audit, print_msg_and_wait_for_key, read_input};
+
+use autd3::{
+    core::{common::ULTRASOUND_PERIOD, link::Link},
+    prelude::*,
+};
+
+// Intensity silencer completion steps (about 0.8s); a divisor of 255 * 256 so that the intensity
+// ramps linearly
+const STEPS: u32 = 255 * 256 / 2;
+
+// Quadratic so that filtering the intensity and filtering the pulse width give different ramps
+fn pulse_width(i: Intensity) -> PulseWidth<9, u16> {
+    let x = i.0 as f32 / 255.;
+    PulseWidth::from_duty(0.5 * x * x).unwrap()
+}
+
+// The v12.1 firmware has no silencer target selection; the silencer always filters the intensity
+// and the pulse width encoder is applied to the filtered value
+pub fn silencer_pwe_test<L: Link + 'static>(
+    autd: &mut Controller<L, firmware::V12_1>,
+) -> anyhow::Result<()> {
+    autd.send(GPIOOutputs::new(|dev, gpio| match gpio {
+        GPIOOut::O0 => Some(GPIOOutputType::PwmOut(&dev[0])),
+        _ => None,
+    }))?;
+    autd.send(PulseWidthEncoder::new(|_| pulse_width))?;
+    autd.send(Silencer::disable())?;
+    autd.send((Static::default(), Uniform::new(Intensity::MIN, Phase::ZERO)))?;
+    autd.send(Silencer::new(FixedCompletionTime {
+        intensity: ULTRASOUND_PERIOD * STEPS,
+        phase: ULTRASOUND_PERIOD * 40,
+        strict: false,
+    }))?;
+
+    match audit::emulators(autd) {
+        // The emulator exposes the settings but does not model the order of the silencer and the
+        // encoder, so the ordering itself is only verified on hardware below
+        Some(emulators) => {
+            emulators.iter().for_each(|cpu| {
+                let fpga = cpu.fpga();
+                assert_eq!(
+                    (0..=0xFF)
+                        .map(|i| pulse_width(Intensity(i)))
+                        .collect::<Vec<_>>(),
+                    fpga.pulse_width_encoder_table()
+                );
+                assert!(fpga.silencer_fixed_completion_steps_mode());
+                assert_eq!(
+                    STEPS,
+                    fpga.silencer_completion_steps().intensity.get() as u32
+                );
+            });
+            autd.send(Uniform::new(Intensity::MAX, Phase::ZERO))?;
+        }
+        None => {
+            print_msg_and_wait_for_key(
+                "次に, Enterを押した後GPIO[0]出力を観察し, Duty比が約0.8秒かけて0%から50%まで変化することを確認する",
+            );
+            autd.send(Uniform::new(Intensity::MAX, Phase::ZERO))?;
+            let duty = read_input("約0.4秒後のDuty比[%]")?;
+            let duty = duty
+                .parse::<f32>()
+                .with_context(|| format!("Duty比の値が不正です: {:?}", duty))?;
+            // Filtered before encoding, the midpoint follows the encoder curve (about 12.5%);
+            // filtering the pulse width instead would give half of the final duty (about 25%)
+            let to_duty = |i: Intensity| pulse_width(i).pulse_width() as f32 / 512. * 100.;
+            let encoded = to_duty(Intensity(0xFF / 2));
+            let filtered = to_duty(Intensity::MAX) / 2.;
+            assert!(
+                (duty - encoded).abs() < (duty - filtered).abs(),
+                "duty at the midpoint {}% is closer to {}% (silencer applied to the pulse width) than to {}%",
+                duty,
+                filtered,
+                encoded
+            );
+            print_msg_and_wait_for_key("Duty比が50%であること");
+        }
+    }
+
+    autd.send(Silencer::disable())?;
+    autd.send(PulseWidthEncoder::default())?;
+    autd.send(GPIOOutputs::new(|_dev, gpio| match gpio {
+        GPIOOut::O0 => Some(GPIOOutputType::BaseSignal),
+        _ => None,
+    }))?;
+    autd.send(Clear::new())?;
+
+    Ok(())
+}
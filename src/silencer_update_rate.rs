@@ -0,0 +1,244 @@
+use std::num::NonZeroU16;
+
+use crate::{assert_no_mismatches, audit, print_msg_and_wait_for_key, trajectory::Trajectory};
+
+use autd3::{
+    core::{
+        common::{SILENCER_STEPS_INTENSITY_DEFAULT, SILENCER_STEPS_PHASE_DEFAULT},
+        link::Link,
+    },
+    driver::datagram::FixedCompletionSteps,
+    prelude::*,
+};
+
+// The update rate is the change per ultrasound period in units of 1/256, so these take as long as
+// the default completion steps for a full-scale change
+const UPDATE_RATE_INTENSITY_DEFAULT: u16 =
+    (256 * 256 / SILENCER_STEPS_INTENSITY_DEFAULT as u32) as u16;
+const UPDATE_RATE_PHASE_DEFAULT: u16 = (256 * 256 / SILENCER_STEPS_PHASE_DEFAULT as u32) as u16;
+
+fn update_rate(intensity: u16, phase: u16) -> FixedUpdateRate {
+    FixedUpdateRate {
+        intensity: NonZeroU16::new(intensity).unwrap(),
+        phase: NonZeroU16::new(phase).unwrap(),
+    }
+}
+
+fn send_update_rate<L: Link + 'static>(
+    autd: &mut Controller<L, firmware::V12_1>,
+    config: FixedUpdateRate,
+    msg: &str,
+) -> anyhow::Result<()> {
+    autd.send(Silencer::new(config))?;
+    match audit::emulators(autd) {
+        Some(emulators) => emulators.iter().for_each(|cpu| {
+            assert!(cpu.fpga().silencer_fixed_update_rate_mode());
+            assert_eq!(config, cpu.fpga().silencer_update_rate());
+        }),
+        None => print_msg_and_wait_for_key(msg),
+    }
+    Ok(())
+}
+
+fn noise_comparison<L: Link + 'static>(
+    autd: &mut Controller<L, firmware::V12_1>,
+) -> anyhow::Result<()> {
+    send_update_rate(
+        autd,
+        update_rate(
+            UPDATE_RATE_INTENSITY_DEFAULT / 2,
+            UPDATE_RATE_PHASE_DEFAULT / 2,
+        ),
+        "ノイズが小さくなったこと",
+    )?;
+    send_update_rate(
+        autd,
+        update_rate(UPDATE_RATE_INTENSITY_DEFAULT, UPDATE_RATE_PHASE_DEFAULT),
+        "ノイズが大きくなったこと",
+    )?;
+    send_update_rate(
+        autd,
+        update_rate(
+            UPDATE_RATE_INTENSITY_DEFAULT * 2,
+            UPDATE_RATE_PHASE_DEFAULT * 2,
+        ),
+        "ノイズが大きくなったこと",
+    )?;
+    send_update_rate(
+        autd,
+        update_rate(u16::MAX, u16::MAX),
+        "ノイズが大きくなったこと",
+    )?;
+    Ok(())
+}
+
+pub fn silencer_update_rate_test<L: Link + 'static>(
+    autd: &mut Controller<L, firmware::V12_1>,
+) -> anyhow::Result<()> {
+    let default = update_rate(UPDATE_RATE_INTENSITY_DEFAULT, UPDATE_RATE_PHASE_DEFAULT);
+    let center = autd.geometry().center() + Vector3::new(0., 0., 150.0 * mm);
+
+    // Modulation
+    {
+        send_update_rate(autd, default, "Silencerが更新レートモードに設定されたこと")?;
+        autd.send((
+            Sine::new(
+                150. * Hz,
+                SineOption {
+                    sampling_config: SamplingConfig::new(NonZeroU16::new(20).unwrap()),
+                    ..Default::default()
+                },
+            ),
+            Focus::new(center, Default::default()),
+        ))?;
+        print_msg_and_wait_for_key("150HzのAMが適用されていること");
+        noise_comparison(autd)?;
+    }
+
+    // FociSTM
+    {
+        send_update_rate(autd, default, "ノイズが小さくなったこと")?;
+        autd.send((
+            Static::default(),
+            FociSTM::new(
+                Trajectory::circle(center, 30.0 * mm)
+                    .points(10)
+                    .into_iter()
+                    .map(|p| ControlPoint::new(p, Phase::ZERO))
+                    .collect::<Vec<_>>(),
+                50. * Hz,
+            ),
+        ))?;
+        print_msg_and_wait_for_key("50HzのFociSTMが適用されていること");
+        noise_comparison(autd)?;
+    }
+
+    // GainSTM
+    {
+        send_update_rate(autd, default, "ノイズが小さくなったこと")?;
+        autd.send(GainSTM::new(
+            Trajectory::circle(center, 30.0 * mm)
+                .points(10)
+                .into_iter()
+                .map(|p| Focus::new(p, Default::default()))
+                .collect::<Vec<_>>(),
+            50. * Hz,
+            Default::default(),
+        ))?;
+        print_msg_and_wait_for_key("50HzのGainSTMが適用されていること");
+        noise_comparison(autd)?;
+    }
+
+    // The update rate mode never constrains the sampling configuration, whatever the previous
+    // completion steps setting was
+    let mut mismatches = Vec::new();
+    for (name, before) in [
+        ("disabled", Silencer::disable()),
+        // Known defect: the firmware keeps the strict flag and minimum divides of the previous
+        // completion steps setting in the update rate mode, so this case fails with
+        // InvalidSilencerSettings
+        ("default (known defect)", Silencer::default()),
+        (
+            "non-strict default",
+            Silencer::new(FixedCompletionSteps {
+                strict: false,
+                ..Default::default()
+            }),
+        ),
+    ] {
+        autd.send((Static::default(), Null::new()))?;
+        autd.send(before)?;
+        autd.send(Silencer::new(default))?;
+
+        let min = SamplingConfig::new(NonZeroU16::MIN);
+        let results = [
+            (
+                "Modulation",
+                autd.send(Sine::new(
+                    100. * Hz,
+                    SineOption {
+                        sampling_config: min,
+                        ..Default::default()
+                    },
+                )),
+            ),
+            (
+                "Modulation S1",
+                autd.send(WithSegment {
+                    inner: Sine::new(
+                        100. * Hz,
+                        SineOption {
+                            sampling_config: min,
+                            ..Default::default()
+                        },
+                    ),
+                    segment: Segment::S1,
+                    transition_mode: Some(TransitionMode::Immediate),
+                }),
+            ),
+            (
+                "FociSTM",
+                autd.send(FociSTM::new(
+                    (0..2).map(|_| ControlPoint::default()).collect::<Vec<_>>(),
+                    min,
+                )),
+            ),
+            (
+                "FociSTM S1",
+                autd.send(WithSegment {
+                    inner: FociSTM::new(
+                        (0..2).map(|_| ControlPoint::default()).collect::<Vec<_>>(),
+                        min,
+                    ),
+                    segment: Segment::S1,
+                    transition_mode: Some(TransitionMode::Immediate),
+                }),
+            ),
+            (
+                "GainSTM",
+                autd.send(GainSTM::new(
+                    (0..2).map(|_| Null::new()).collect::<Vec<_>>(),
+                    min,
+                    Default::default(),
+                )),
+            ),
+            (
+                "GainSTM S1",
+                autd.send(WithSegment {
+                    inner: GainSTM::new(
+                        (0..2).map(|_| Null::new()).collect::<Vec<_>>(),
+                        min,
+                        Default::default(),
+                    ),
+                    segment: Segment::S1,
+                    transition_mode: Some(TransitionMode::Immediate),
+                }),
+            ),
+        ];
+        let applied = results.iter().all(|(_, res)| res.is_ok());
+        results
+            .into_iter()
+            .filter(|(_, res)| res.is_err())
+            .for_each(|(target, res)| {
+                mismatches.push(format!(
+                    "{} after {} silencer: expected Ok(()), actual {:?}",
+                    target, name, res
+                ))
+            });
+
+        // Leaving the update rate mode validates the current sampling configuration again
+        if applied {
+            assert_eq!(
+                Err(AUTDDriverError::InvalidSilencerSettings),
+                autd.send(Silencer::default())
+            );
+        }
+        autd.send(Silencer::disable())?;
+    }
+
+    assert_no_mismatches(&mismatches, "update rate silencer test");
+
+    autd.send(Clear::new())?;
+
+    Ok(())
+}
@@ -0,0 +1,172 @@
+use crate::{assert_no_mismatches, audit, print_msg_and_wait_for_key, trajectory::Trajectory};
+
+use autd3::{
+    core::{common::STM_BUF_SIZE_MIN, geometry::Transducer, link::Link},
+    driver::firmware::v12_1::fpga::FOCI_STM_BUF_SIZE_MAX,
+    prelude::*,
+};
+
+// The FPGA superposes the foci in fixed-point arithmetic, so the phase only approximately
+// equals the ideal one
+const PHASE_TOLERANCE: u8 = 4;
+
+// The foci of each control point are spread evenly around the circle
+fn control_points<const N: usize>(center: Point3, size: usize) -> Vec<ControlPoints<N>> {
+    let points = Trajectory::circle(center, 30.0 * mm).points(size * N);
+    (0..size)
+        .map(|i| {
+            ControlPoints::new(
+                std::array::from_fn(|j| {
+                    ControlPoint::new(
+                        points[(i * N + j * size) % points.len()],
+                        Phase((j * 0x20) as u8),
+                    )
+                }),
+                Intensity(0xFF - (i % 4) as u8 * 0x20),
+            )
+        })
+        .collect()
+}
+
+fn expected_phase(tr: &Transducer, wavelength: f32, points: &[ControlPoint]) -> Option<u8> {
+    // Only the phase offsets relative to the first focus are sent to the device
+    let (sin, cos) = points.iter().fold((0f32, 0f32), |(sin, cos), p| {
+        let q = -(p.point.coords - tr.position().coords).norm() / wavelength * 2.0 * PI
+            - (p.phase_offset - points[0].phase_offset).radian();
+        (sin + q.sin(), cos + q.cos())
+    });
+    // The phase of a nearly cancelled field is not meaningful
+    ((sin * sin + cos * cos).sqrt() > 0.5 * points.len() as f32).then(|| {
+        (sin.atan2(cos) / (2.0 * PI) * 256.0)
+            .round()
+            .rem_euclid(256.) as u8
+    })
+}
+
+fn check_foci<const N: usize, L: Link + 'static>(
+    autd: &mut Controller<L, firmware::V12_1>,
+    size: usize,
+) -> anyhow::Result<Vec<String>> {
+    let center = autd.geometry().center() + Vector3::new(0., 0., 150.0 * mm);
+    let points = control_points::<N>(center, size);
+    let config = SamplingConfig::new(1.0 * Hz * size as f32).into_nearest();
+
+    let mut mismatches = Vec::new();
+    for segment in [Segment::S0, Segment::S1] {
+        autd.send(WithSegment {
+            inner: FociSTM::new(points.clone(), config),
+            segment,
+            transition_mode: Some(TransitionMode::Immediate),
+        })?;
+
+        let wavelength = autd.environment.wavelength();
+        match audit::emulators(autd) {
+            Some(emulators) => emulators.iter().for_each(|cpu| {
+                let fpga = cpu.fpga();
+                let dev = &autd.geometry()[cpu.idx()];
+                let mut check = |name: String, expect: String, actual: String| {
+                    if expect != actual {
+                        mismatches.push(format!(
+                            "{} foci, size {}, {:?}: device {} {} expected {}, actual {}",
+                            N,
+                            size,
+                            segment,
+                            cpu.idx(),
+                            name,
+                            expect,
+                            actual
+                        ));
+                    }
+                };
+                check(
+                    "num foci".to_string(),
+                    N.to_string(),
+                    fpga.num_foci(segment).to_string(),
+                );
+                check(
+                    "cycle".to_string(),
+                    size.to_string(),
+                    fpga.stm_cycle(segment).to_string(),
+                );
+                check(
+                    "gain mode".to_string(),
+                    false.to_string(),
+                    fpga.is_stm_gain_mode(segment).to_string(),
+                );
+                [0, size / 2, size - 1].into_iter().for_each(|idx| {
+                    let drives = fpga.drives_at(segment, idx);
+                    check(
+                        format!("point {} intensities", idx),
+                        format!("{:?}", vec![points[idx].intensity; dev.num_transducers()]),
+                        format!(
+                            "{:?}",
+                            drives.iter().map(|d| d.intensity).collect::<Vec<_>>()
+                        ),
+                    );
+                    let phase_errors = dev
+                        .iter()
+                        .zip(drives.iter())
+                        .filter(|(tr, d)| {
+                            expected_phase(tr, wavelength, &points[idx].points).is_some_and(
+                                |expect| {
+                                    let diff = expect.wrapping_sub(d.phase.0);
+                                    diff.min(diff.wrapping_neg()) > PHASE_TOLERANCE
+                                },
+                            )
+                        })
+                        .count();
+                    check(
+                        format!("point {} phase errors", idx),
+                        0.to_string(),
+                        phase_errors.to_string(),
+                    );
+                });
+            }),
+            None => print_msg_and_wait_for_key(&format!(
+                "{:?}: 各デバイスの中心から150mm直上を中心に半径30mmの円周上に{}個の焦点が同時に生成され, {:?}周期で回転していること",
+                segment,
+                N,
+                config.period()? * size as u32
+            )),
+        }
+
+        std::thread::sleep(std::time::Duration::from_millis(100));
+        autd.fpga_state()?.iter().for_each(|state| {
+            assert!(state.is_some());
+            assert_eq!(Some(segment), state.unwrap().current_stm_segment());
+        });
+    }
+
+    Ok(mismatches)
+}
+
+fn check_sizes<const N: usize, L: Link + 'static>(
+    autd: &mut Controller<L, firmware::V12_1>,
+) -> anyhow::Result<Vec<String>> {
+    let mut mismatches = check_foci::<N, _>(autd, STM_BUF_SIZE_MIN)?;
+    mismatches.extend(check_foci::<N, _>(autd, FOCI_STM_BUF_SIZE_MAX / N)?);
+    Ok(mismatches)
+}
+
+pub fn stm_foci_coverage_test<L: Link + 'static>(
+    autd: &mut Controller<L, firmware::V12_1>,
+) -> anyhow::Result<()> {
+    autd.send(Static::default())?;
+    autd.send(Silencer::disable())?;
+
+    let mut mismatches = Vec::new();
+    mismatches.extend(check_sizes::<1, _>(autd)?);
+    mismatches.extend(check_sizes::<2, _>(autd)?);
+    mismatches.extend(check_sizes::<3, _>(autd)?);
+    mismatches.extend(check_sizes::<4, _>(autd)?);
+    mismatches.extend(check_sizes::<5, _>(autd)?);
+    mismatches.extend(check_sizes::<6, _>(autd)?);
+    mismatches.extend(check_sizes::<7, _>(autd)?);
+    mismatches.extend(check_sizes::<8, _>(autd)?);
+
+    assert_no_mismatches(&mismatches, "FociSTM coverage test");
+
+    autd.send(Clear::new())?;
+
+    Ok(())
+}
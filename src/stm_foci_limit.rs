@@ -0,0 +1,252 @@
+use crate::{assert_no_mismatches, audit};
+
+use autd3::{
+    core::{
+        common::STM_BUF_SIZE_MIN,
+        geometry::{Device, Transducer},
+        link::Link,
+    },
+    driver::firmware::{driver::Driver, v12_1::fpga::FOCI_STM_BUF_SIZE_MAX},
+    prelude::*,
+};
+
+fn stm_state<L: Link + 'static>(
+    autd: &Controller<L, firmware::V12_1>,
+) -> Option<Vec<(Segment, usize, usize)>> {
+    audit::emulators(autd).map(|emulators| {
+        emulators
+            .iter()
+            .map(|cpu| {
+                let fpga = cpu.fpga();
+                (
+                    fpga.current_stm_segment(),
+                    fpga.stm_cycle(Segment::S0),
+                    fpga.stm_cycle(Segment::S1),
+                )
+            })
+            .collect()
+    })
+}
+
+fn translate(dev: &Device, offset: Vector3) -> Device {
+    Device::new(
+        *dev.rotation(),
+        dev.iter()
+            .map(|tr| Transducer::new(tr.position() + offset))
+            .collect(),
+    )
+}
+
+// The driver range-checks each focus in the coordinate system of each device
+fn out_of_range(dev: &Device, p: Point3) -> AUTDDriverError {
+    let p = dev.inv().transform_point(&p);
+    AUTDDriverError::FociSTMPointOutOfRange(p.x, p.y, p.z, firmware::V12_1.firmware_limits())
+}
+
+fn check_send<L: Link + 'static, const N: usize>(
+    autd: &mut Controller<L, firmware::V12_1>,
+    name: &str,
+    segment: Segment,
+    points: Vec<ControlPoints<N>>,
+    expect: Result<(), AUTDDriverError>,
+) -> Option<String> {
+    let before = stm_state(autd);
+    let res = autd.send(WithSegment {
+        inner: FociSTM::new(points, SamplingConfig::FREQ_4K),
+        segment,
+        transition_mode: Some(TransitionMode::Immediate),
+    });
+    let expect = format!("{:?}", expect);
+    let actual = format!("{:?}", res);
+    if expect != actual {
+        return Some(format!(
+            "{}, {:?}: expected {}, actual {}",
+            name, segment, expect, actual
+        ));
+    }
+    if res.is_err() && before != stm_state(autd) {
+        return Some(format!(
+            "{}, {:?}: device state changed by a rejected STM",
+            name, segment
+        ));
+    }
+    None
+}
+
+pub fn stm_foci_limit_test<L: Link + 'static>(
+    autd: &mut Controller<L, firmware::V12_1>,
+) -> anyhow::Result<()> {
+    autd.send(Static::default())?;
+    autd.send(Silencer::disable())?;
+
+    let limits = firmware::V12_1.firmware_limits();
+    let unit = limits.foci_stm_fixed_num_unit;
+    let center = autd.geometry().center() + Vector3::new(0., 0., 150.0 * mm);
+    let single = |points: Vec<Point3>| {
+        points
+            .into_iter()
+            .map(|p| ControlPoints::<1>::from(ControlPoint::from(p)))
+            .collect::<Vec<_>>()
+    };
+
+    let mut mismatches = Vec::new();
+    for segment in [Segment::S0, Segment::S1] {
+        autd.send(WithSegment {
+            inner: FociSTM::new(vec![center; 2], SamplingConfig::FREQ_4K),
+            segment,
+            transition_mode: Some(TransitionMode::Immediate),
+        })?;
+
+        // Known defect: the host does not reject an empty STM, so the firmware fails to confirm it
+        // (ConfirmResponseFailed) instead of FociSTMTotalSizeOutOfRange
+        mismatches.extend(check_send(
+            autd,
+            "size 0 (known defect)",
+            segment,
+            single(vec![]),
+            Err(AUTDDriverError::FociSTMTotalSizeOutOfRange(0, limits)),
+        ));
+        mismatches.extend(check_send(
+            autd,
+            "size MIN - 1",
+            segment,
+            single(vec![center; STM_BUF_SIZE_MIN - 1]),
+            Err(AUTDDriverError::FociSTMTotalSizeOutOfRange(
+                STM_BUF_SIZE_MIN - 1,
+                limits,
+            )),
+        ));
+        mismatches.extend(check_send(
+            autd,
+            "size MAX",
+            segment,
+            single(vec![center; FOCI_STM_BUF_SIZE_MAX]),
+            Ok(()),
+        ));
+        mismatches.extend(check_send(
+            autd,
+            "size MAX + 1",
+            segment,
+            single(vec![center; FOCI_STM_BUF_SIZE_MAX + 1]),
+            Err(AUTDDriverError::FociSTMTotalSizeOutOfRange(
+                FOCI_STM_BUF_SIZE_MAX + 1,
+                limits,
+            )),
+        ));
+        mismatches.extend(check_send(
+            autd,
+            "8 foci, size MAX / 8 + 1",
+            segment,
+            vec![
+                ControlPoints::<8>::from([ControlPoint::from(center); 8]);
+                FOCI_STM_BUF_SIZE_MAX / 8 + 1
+            ],
+            Err(AUTDDriverError::FociSTMTotalSizeOutOfRange(
+                (FOCI_STM_BUF_SIZE_MAX / 8 + 1) * 8,
+                limits,
+            )),
+        ));
+
+        let dev = &autd.geometry()[0];
+        let to_global = dev.inv().inverse();
+        let boundaries = [
+            (
+                "x",
+                Vector3::x(),
+                limits.foci_stm_fixed_num_lower_x(),
+                limits.foci_stm_fixed_num_upper_x(),
+            ),
+            (
+                "y",
+                Vector3::y(),
+                limits.foci_stm_fixed_num_lower_y(),
+                limits.foci_stm_fixed_num_upper_y(),
+            ),
+            (
+                "z",
+                Vector3::z(),
+                limits.foci_stm_fixed_num_lower_z(),
+                limits.foci_stm_fixed_num_upper_z(),
+            ),
+        ];
+        let cases = boundaries
+            .iter()
+            .flat_map(|&(axis, dir, lower, upper)| {
+                [
+                    (format!("{} lower", axis), dir * lower as f32 * unit, true),
+                    (
+                        format!("{} lower - 1", axis),
+                        dir * (lower - 1) as f32 * unit,
+                        false,
+                    ),
+                    (format!("{} upper", axis), dir * upper as f32 * unit, true),
+                    (
+                        format!("{} upper + 1", axis),
+                        dir * (upper + 1) as f32 * unit,
+                        false,
+                    ),
+                ]
+            })
+            .map(|(name, local, valid)| {
+                let p = to_global.transform_point(&Point3::from(local));
+                (name, p, valid.then_some(()).ok_or(out_of_range(dev, p)))
+            })
+            // Known defect: non-finite coordinates are rounded to 0 instead of being rejected, so these
+            // cases are sent successfully
+            .chain(
+                [
+                    ("NaN", Point3::new(f32::NAN, center.y, center.z)),
+                    ("+inf", Point3::new(f32::INFINITY, center.y, center.z)),
+                    ("-inf", Point3::new(center.x, f32::NEG_INFINITY, center.z)),
+                ]
+                .map(|(name, p)| {
+                    (
+                        format!("{} (known defect)", name),
+                        p,
+                        Err(out_of_range(dev, p)),
+                    )
+                }),
+            )
+            .collect::<Vec<_>>();
+        for (name, p, expect) in cases {
+            mismatches.extend(check_send(
+                autd,
+                &name,
+                segment,
+                single(vec![center, p]),
+                expect,
+            ));
+        }
+
+        // Move device 1 so that a focus near the edge of the range of device 0 overflows only on
+        // device 1
+        let offset = Vector3::new(-200.0 * mm, 0., 0.);
+        autd.reconfigure(|dev| match dev.idx() {
+            1 => translate(dev, offset),
+            _ => translate(dev, Vector3::zeros()),
+        });
+        let p = to_global.transform_point(&Point3::new(
+            limits.foci_stm_fixed_num_upper_x() as f32 * unit - 100.0 * mm,
+            0.,
+            0.,
+        ));
+        let expect = Err(out_of_range(&autd.geometry()[1], p));
+        mismatches.extend(check_send(
+            autd,
+            "valid only on device 0",
+            segment,
+            single(vec![center, p]),
+            expect,
+        ));
+        autd.reconfigure(|dev| match dev.idx() {
+            1 => translate(dev, -offset),
+            _ => translate(dev, Vector3::zeros()),
+        });
+    }
+
+    assert_no_mismatches(&mismatches, "FociSTM limit test");
+
+    autd.send(Clear::new())?;
+
+    Ok(())
+}
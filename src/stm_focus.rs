@@ -1,4 +1,4 @@
-use crate::print_msg_and_wait_for_key;
+use crate::{print_msg_and_wait_for_key, trajectory::Trajectory};
 
 use autd3::{core::link::Link, driver::firmware::v12_1::fpga::FOCI_STM_BUF_SIZE_MAX, prelude::*};
 
@@ -10,11 +10,10 @@ pub fn stm_focus_test<L: Link>(autd: &mut Controller<L, firmware::V12_1>) -> any
     let point_num = 200;
     let radius = 30.0 * mm;
     let gen_foci = || {
-        (0..point_num).map(|i| {
-            let theta = 2.0 * PI * i as f32 / point_num as f32;
-            let p = radius * Vector3::new(theta.cos(), theta.sin(), 0.0);
-            ControlPoints::<1>::from(ControlPoint::new(center + p, Phase::ZERO))
-        })
+        Trajectory::circle(center, radius)
+            .points(point_num)
+            .into_iter()
+            .map(|p| ControlPoints::<1>::from(ControlPoint::new(p, Phase::ZERO)))
     };
 
     let stm = FociSTM::new(gen_foci().collect::<Vec<_>>(), 0.5 * Hz);
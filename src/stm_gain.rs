@@ -1,4 +1,4 @@
-use crate::print_msg_and_wait_for_key;
+use crate::{print_msg_and_wait_for_key, trajectory::Trajectory};
 
 use autd3::{core::link::Link, prelude::*};
 
@@ -9,11 +9,10 @@ pub fn stm_gain_test<L: Link>(autd: &mut Controller<L, firmware::V12_1>) -> anyh
     let point_num = 200;
     let radius = 30.0 * mm;
     let gen_foci = || {
-        (0..point_num).map(|i| {
-            let theta = 2.0 * PI * i as f32 / point_num as f32;
-            let p = radius * Vector3::new(theta.cos(), theta.sin(), 0.0);
-            Focus::new(center + p, Default::default())
-        })
+        Trajectory::circle(center, radius)
+            .points(point_num)
+            .into_iter()
+            .map(|p| Focus::new(p, Default::default()))
     };
 
     let stm = GainSTM::new(gen_foci().collect::<Vec<_>>(), 0.5 * Hz, Default::default());
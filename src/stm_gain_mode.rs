@@ -0,0 +1,209 @@
+use std::{num::NonZeroU16, time::Duration};
+
+use crate::{assert_no_mismatches, audit, print_msg_and_wait_for_key, trajectory::Trajectory};
+
+use autd3::{
+    core::{common::ULTRASOUND_PERIOD, link::Link},
+    driver::firmware::{driver::Driver, v12_1::fpga::GAIN_STM_BUF_SIZE_MAX},
+    prelude::*,
+};
+
+const SIZES: [usize; 4] = [3, 10, GAIN_STM_BUF_SIZE_MAX - 1, GAIN_STM_BUF_SIZE_MAX];
+const DIVIDE: u16 = 40;
+
+fn foci(center: Point3, size: usize) -> Vec<Focus> {
+    Trajectory::circle(center, 30.0 * mm)
+        .points(size)
+        .into_iter()
+        .enumerate()
+        .map(|(i, p)| {
+            Focus::new(
+                p,
+                FocusOption {
+                    intensity: Intensity(0x40 + (i * 0xBF / size) as u8),
+                    phase_offset: Phase((i * 7) as u8),
+                },
+            )
+        })
+        .collect()
+}
+
+fn reconstruct(mode: GainSTMMode, d: Drive) -> Drive {
+    match mode {
+        GainSTMMode::PhaseIntensityFull => d,
+        GainSTMMode::PhaseFull => Drive {
+            phase: d.phase,
+            intensity: Intensity::MAX,
+        },
+        GainSTMMode::PhaseHalf => {
+            let phase = d.phase.0 >> 4;
+            Drive {
+                phase: Phase((phase << 4) | phase),
+                intensity: Intensity::MAX,
+            }
+        }
+    }
+}
+
+fn check_mode<L: Link + 'static>(
+    autd: &mut Controller<L, firmware::V12_1>,
+    mode: GainSTMMode,
+    segment: Segment,
+    size: usize,
+) -> anyhow::Result<Vec<String>> {
+    let center = autd.geometry().center() + Vector3::new(0., 0., 150.0 * mm);
+    let config = SamplingConfig::new(NonZeroU16::new(DIVIDE).unwrap());
+    autd.send(WithSegment {
+        inner: GainSTM::new(foci(center, size), config, GainSTMOption { mode }),
+        segment,
+        transition_mode: Some(TransitionMode::Immediate),
+    })?;
+
+    let expect = foci(center, size)
+        .into_iter()
+        .map(|g| -> anyhow::Result<_> {
+            Ok(autd
+                .inspect(g)?
+                .iter()
+                .map(|r| {
+                    r.as_ref()
+                        .unwrap()
+                        .data
+                        .iter()
+                        .map(|&d| reconstruct(mode, d))
+                        .collect::<Vec<_>>()
+                })
+                .collect::<Vec<_>>())
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    let mut mismatches = Vec::new();
+    let period = ULTRASOUND_PERIOD * DIVIDE as u32;
+    match audit::emulators_mut(autd) {
+        Some(emulators) => emulators.iter_mut().for_each(|cpu| {
+            let dev_idx = cpu.idx();
+            let mut check = |name: String, expect: String, actual: String| {
+                if expect != actual {
+                    mismatches.push(format!(
+                        "{:?}, {:?}, size {}: device {} {} expected {}, actual {}",
+                        mode, segment, size, dev_idx, name, expect, actual
+                    ));
+                }
+            };
+            check(
+                "gain mode".to_string(),
+                true.to_string(),
+                cpu.fpga().is_stm_gain_mode(segment).to_string(),
+            );
+            check(
+                "cycle".to_string(),
+                size.to_string(),
+                cpu.fpga().stm_cycle(segment).to_string(),
+            );
+            check(
+                "divide".to_string(),
+                DIVIDE.to_string(),
+                cpu.fpga().stm_freq_divide(segment).to_string(),
+            );
+            expect.iter().enumerate().for_each(|(idx, expect)| {
+                let actual = cpu.fpga().drives_at(segment, idx);
+                check(
+                    format!("first mismatched drive of frame {}", idx),
+                    "None".to_string(),
+                    format!(
+                        "{:?}",
+                        expect[dev_idx]
+                            .iter()
+                            .zip(actual.iter())
+                            .position(|(a, b)| a != b)
+                    ),
+                );
+            });
+            [1, size as u32 - 1, size as u32 + 1]
+                .into_iter()
+                .for_each(|k| {
+                    let t = DcSysTime::ZERO + period * k;
+                    cpu.update_with_sys_time(t - Duration::from_nanos(1));
+                    let before = cpu.fpga().current_stm_idx();
+                    cpu.update_with_sys_time(t);
+                    let after = cpu.fpga().current_stm_idx();
+                    check(
+                        format!("index around {:?}", period * k),
+                        format!("{:?}", ((k as usize - 1) % size, k as usize % size)),
+                        format!("{:?}", (before, after)),
+                    );
+                });
+        }),
+        None => {
+            if size == 10 {
+                print_msg_and_wait_for_key(&format!(
+                    "{:?}, {:?}: 各デバイスの中心から150mm直上を中心に半径30mmの円周上にSTMが適用され, GPIO[1]のパルス周期が{:?}であること",
+                    mode,
+                    segment,
+                    period * size as u32
+                ));
+            }
+        }
+    }
+
+    std::thread::sleep(Duration::from_millis(100));
+    autd.fpga_state()?.iter().for_each(|state| {
+        assert!(state.is_some());
+        let state = state.unwrap();
+        assert_eq!(None, state.current_gain_segment());
+        assert_eq!(Some(segment), state.current_stm_segment());
+    });
+
+    Ok(mismatches)
+}
+
+pub fn stm_gain_mode_test<L: Link + 'static>(
+    autd: &mut Controller<L, firmware::V12_1>,
+) -> anyhow::Result<()> {
+    autd.send(Static::default())?;
+    autd.send(Silencer::disable())?;
+    autd.send(GPIOOutputs::new(|_dev, gpio| match gpio {
+        GPIOOut::O1 => Some(GPIOOutputType::StmIdx(0)),
+        _ => None,
+    }))?;
+
+    let center = autd.geometry().center() + Vector3::new(0., 0., 150.0 * mm);
+    let mut mismatches = Vec::new();
+    for mode in [
+        GainSTMMode::PhaseIntensityFull,
+        GainSTMMode::PhaseFull,
+        GainSTMMode::PhaseHalf,
+    ] {
+        for segment in [Segment::S0, Segment::S1] {
+            for size in SIZES {
+                mismatches.extend(check_mode(autd, mode, segment, size)?);
+            }
+
+            assert_eq!(
+                Err(AUTDDriverError::GainSTMSizeOutOfRange(
+                    GAIN_STM_BUF_SIZE_MAX + 1,
+                    firmware::V12_1.firmware_limits()
+                )),
+                autd.send(WithSegment {
+                    inner: GainSTM::new(
+                        foci(center, GAIN_STM_BUF_SIZE_MAX + 1),
+                        SamplingConfig::new(NonZeroU16::new(DIVIDE).unwrap()),
+                        GainSTMOption { mode },
+                    ),
+                    segment,
+                    transition_mode: Some(TransitionMode::Immediate),
+                })
+            );
+        }
+    }
+
+    assert_no_mismatches(&mismatches, "GainSTM mode test");
+
+    autd.send(GPIOOutputs::new(|_dev, gpio| match gpio {
+        GPIOOut::O0 => Some(GPIOOutputType::BaseSignal),
+        _ => None,
+    }))?;
+    autd.send(Clear::new())?;
+
+    Ok(())
+}
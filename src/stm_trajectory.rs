@@ -0,0 +1,180 @@
+use std::path::Path;
+
+use crate::{
+    assert_no_mismatches, audit, print_msg_and_wait_for_key, read_input,
+    trajectory::{Rate, Trajectory},
+};
+
+use autd3::{core::link::Link, prelude::*};
+
+const SIZE: usize = 200;
+
+fn check_trajectory<L: Link + 'static>(
+    autd: &mut Controller<L, firmware::V12_1>,
+    name: &str,
+    trajectory: &Trajectory,
+) -> anyhow::Result<Vec<String>> {
+    let mut mismatches = Vec::new();
+
+    let speed = Rate::Speed(500.0 * mm);
+    autd.send(WithSegment {
+        inner: trajectory.foci_stm(SIZE, speed),
+        segment: Segment::S0,
+        transition_mode: Some(TransitionMode::Immediate),
+    })?;
+    let config = trajectory.sampling_config(SIZE, speed);
+    match audit::emulators(autd) {
+        Some(emulators) => emulators.iter().for_each(|cpu| {
+            let fpga = cpu.fpga();
+            let actual = (
+                fpga.is_stm_gain_mode(Segment::S0),
+                fpga.stm_cycle(Segment::S0),
+                fpga.stm_freq_divide(Segment::S0),
+            );
+            let expect = (false, SIZE, config.divide().unwrap());
+            if expect != actual {
+                mismatches.push(format!(
+                    "{} FociSTM: device {} expected {:?}, actual {:?}",
+                    name,
+                    cpu.idx(),
+                    expect,
+                    actual
+                ));
+            }
+        }),
+        None => print_msg_and_wait_for_key(&format!(
+            "{}: 焦点が{:?}周期で軌道上を移動していること",
+            name,
+            config.period()? * SIZE as u32
+        )),
+    }
+
+    let freq = Rate::Freq(1.0 * Hz);
+    autd.send(WithSegment {
+        inner: trajectory.gain_stm(SIZE, freq, Default::default()),
+        segment: Segment::S1,
+        transition_mode: Some(TransitionMode::Immediate),
+    })?;
+    let config = trajectory.sampling_config(SIZE, freq);
+    let points = trajectory.points(SIZE);
+    let expect = [0, SIZE / 2, SIZE - 1]
+        .into_iter()
+        .map(|idx| -> anyhow::Result<_> {
+            Ok((
+                idx,
+                autd.inspect(Focus::new(points[idx], Default::default()))?,
+            ))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    match audit::emulators(autd) {
+        Some(emulators) => emulators.iter().for_each(|cpu| {
+            let fpga = cpu.fpga();
+            let actual = (
+                fpga.is_stm_gain_mode(Segment::S1),
+                fpga.stm_cycle(Segment::S1),
+                fpga.stm_freq_divide(Segment::S1),
+            );
+            let expect_state = (true, SIZE, config.divide().unwrap());
+            if expect_state != actual {
+                mismatches.push(format!(
+                    "{} GainSTM: device {} expected {:?}, actual {:?}",
+                    name,
+                    cpu.idx(),
+                    expect_state,
+                    actual
+                ));
+            }
+            expect.iter().for_each(|(idx, r)| {
+                if r[cpu.idx()].as_ref().unwrap().data != fpga.drives_at(Segment::S1, *idx) {
+                    mismatches.push(format!(
+                        "{} GainSTM: device {} drives of frame {} differ from Focus",
+                        name,
+                        cpu.idx(),
+                        idx
+                    ));
+                }
+            });
+        }),
+        None => {
+            print_msg_and_wait_for_key(&format!("{}: 焦点が1Hzで軌道上を移動していること", name))
+        }
+    }
+
+    std::thread::sleep(std::time::Duration::from_millis(100));
+    autd.fpga_state()?.iter().for_each(|state| {
+        assert!(state.is_some());
+        assert_eq!(Some(Segment::S1), state.unwrap().current_stm_segment());
+    });
+
+    Ok(mismatches)
+}
+
+pub fn stm_trajectory_test<L: Link + 'static>(
+    autd: &mut Controller<L, firmware::V12_1>,
+) -> anyhow::Result<()> {
+    autd.send(Static::default())?;
+    autd.send(Silencer::disable())?;
+
+    let center = autd.geometry().center() + Vector3::new(0., 0., 150.0 * mm);
+    let mut trajectories = vec![
+        (
+            "直線".to_string(),
+            Trajectory::Line {
+                start: center - 30.0 * mm * Vector3::x(),
+                end: center + 30.0 * mm * Vector3::x(),
+            },
+        ),
+        ("円".to_string(), Trajectory::circle(center, 30.0 * mm)),
+        (
+            "リサジュー".to_string(),
+            Trajectory::Lissajous {
+                center,
+                amplitude: (30.0 * mm, 20.0 * mm),
+                freq: (3, 2),
+                delta: PI / 2.,
+            },
+        ),
+        (
+            "渦巻き".to_string(),
+            Trajectory::Spiral {
+                center,
+                radius: 30.0 * mm,
+                turns: 5,
+            },
+        ),
+        (
+            "8の字".to_string(),
+            Trajectory::FigureEight {
+                center,
+                radius: 30.0 * mm,
+            },
+        ),
+        (
+            "ラスタ".to_string(),
+            Trajectory::Raster {
+                center,
+                width: 60.0 * mm,
+                height: 40.0 * mm,
+                lines: 8,
+            },
+        ),
+    ];
+    let path = read_input("軌道CSVファイルパス (x,y,z[mm], 空欄の場合はスキップ)")?;
+    if !path.is_empty() {
+        trajectories.push((
+            path.clone(),
+            Trajectory::from_csv(Path::new(&path), center)?,
+        ));
+    }
+
+    let mut mismatches = Vec::new();
+    for (name, trajectory) in &trajectories {
+        mismatches.extend(check_trajectory(autd, name, trajectory)?);
+    }
+
+    assert_no_mismatches(&mismatches, "trajectory STM test");
+
+    autd.send(Clear::new())?;
+
+    Ok(())
+}
@@ -0,0 +1,88 @@
+use std::num::NonZeroU16;
+
+use autd3::{
+    core::{common::ULTRASOUND_PERIOD, link::Link},
+    prelude::*,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Target {
+    Modulation,
+    FociSTM,
+    GainSTM,
+}
+
+impl Target {
+    // The smallest divide accepted in strict mode with the given completion steps; STM changes
+    // both the intensity and the phase at every sample
+    pub fn min_divide(&self, intensity: u16, phase: u16) -> u16 {
+        match self {
+            Target::Modulation => intensity,
+            Target::FociSTM | Target::GainSTM => intensity.max(phase),
+        }
+    }
+
+    pub fn swap(&self, segment: Segment, transition_mode: TransitionMode) -> SwapSegment {
+        match self {
+            Target::Modulation => SwapSegment::Modulation(segment, transition_mode),
+            Target::FociSTM => SwapSegment::FociSTM(segment, transition_mode),
+            Target::GainSTM => SwapSegment::GainSTM(segment, transition_mode),
+        }
+    }
+}
+
+pub fn silencer(intensity: u16, phase: u16, strict: bool) -> Silencer<FixedCompletionTime> {
+    Silencer::new(FixedCompletionTime {
+        intensity: ULTRASOUND_PERIOD * intensity as u32,
+        phase: ULTRASOUND_PERIOD * phase as u32,
+        strict,
+    })
+}
+
+// Disables the silencer so that the sampling configuration left by the previous case never makes
+// the next silencer setting invalid
+pub fn reset<L: Link>(autd: &mut Controller<L, firmware::V12_1>) -> anyhow::Result<()> {
+    autd.send((Static::default(), Null::new()))?;
+    autd.send(Silencer::disable())?;
+    Ok(())
+}
+
+pub fn send_target<L: Link>(
+    autd: &mut Controller<L, firmware::V12_1>,
+    target: Target,
+    divide: u16,
+    segment: Segment,
+) -> Result<(), AUTDDriverError> {
+    let config = SamplingConfig::new(NonZeroU16::new(divide).unwrap());
+    let transition_mode = match segment {
+        Segment::S0 => Some(TransitionMode::Immediate),
+        Segment::S1 => None,
+    };
+    match target {
+        Target::Modulation => autd.send(WithSegment {
+            inner: autd3::modulation::Custom {
+                buffer: vec![0xFF, 0x00],
+                sampling_config: config,
+            },
+            segment,
+            transition_mode,
+        }),
+        Target::FociSTM => autd.send(WithSegment {
+            inner: FociSTM::new(
+                (0..2).map(|_| ControlPoint::default()).collect::<Vec<_>>(),
+                config,
+            ),
+            segment,
+            transition_mode,
+        }),
+        Target::GainSTM => autd.send(WithSegment {
+            inner: GainSTM::new(
+                (0..2).map(|_| Null::new()).collect::<Vec<_>>(),
+                config,
+                Default::default(),
+            ),
+            segment,
+            transition_mode,
+        }),
+    }
+}
@@ -0,0 +1,218 @@
+use std::path::Path;
+
+use autd3::{core::common::Freq, prelude::*};
+
+#[derive(Debug, Clone, Copy)]
+pub enum Rate {
+    Freq(Freq<f32>),
+    // Average speed along the trajectory [mm/s]
+    Speed(f32),
+}
+
+#[derive(Debug, Clone)]
+pub enum Trajectory {
+    // Goes from the start to the end and back
+    Line {
+        start: Point3,
+        end: Point3,
+    },
+    Circle {
+        center: Point3,
+        radius: f32,
+    },
+    Lissajous {
+        center: Point3,
+        amplitude: (f32, f32),
+        freq: (u32, u32),
+        delta: f32,
+    },
+    // Spirals outward and back inward
+    Spiral {
+        center: Point3,
+        radius: f32,
+        turns: u32,
+    },
+    FigureEight {
+        center: Point3,
+        radius: f32,
+    },
+    Raster {
+        center: Point3,
+        width: f32,
+        height: f32,
+        lines: usize,
+    },
+    // Closed polyline, traversed at constant speed
+    Points(Vec<Point3>),
+}
+
+impl Trajectory {
+    pub const fn circle(center: Point3, radius: f32) -> Self {
+        Self::Circle { center, radius }
+    }
+
+    // Each line is `x,y,z` in mm relative to `origin`; the first line may be a header
+    pub fn from_csv(path: &Path, origin: Point3) -> anyhow::Result<Self> {
+        let points = std::fs::read_to_string(path)?
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .filter_map(|(i, line)| {
+                let values = line
+                    .split(',')
+                    .map(|v| v.trim().parse::<f32>())
+                    .collect::<Result<Vec<_>, _>>();
+                match values {
+                    Ok(v) if v.len() == 3 => Some(Ok(origin + Vector3::new(v[0], v[1], v[2]) * mm)),
+                    Err(_) if i == 0 => None,
+                    _ => Some(Err(anyhow::anyhow!(
+                        "line {}: expected x,y,z but got {:?}",
+                        i + 1,
+                        line
+                    ))),
+                }
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        if points.is_empty() {
+            anyhow::bail!("CSV file has no points");
+        }
+        Ok(Self::Points(points))
+    }
+
+    fn raster_corners(center: Point3, width: f32, height: f32, lines: usize) -> Vec<Point3> {
+        let origin = center - Vector3::new(width / 2., height / 2., 0.);
+        (0..lines)
+            .flat_map(|i| {
+                let y = if lines > 1 {
+                    height * i as f32 / (lines - 1) as f32
+                } else {
+                    height / 2.
+                };
+                let (a, b) = if i % 2 == 0 { (0., width) } else { (width, 0.) };
+                [
+                    origin + Vector3::new(a, y, 0.),
+                    origin + Vector3::new(b, y, 0.),
+                ]
+            })
+            .collect()
+    }
+
+    fn polyline_at(points: &[Point3], t: f32) -> Point3 {
+        let n = points.len();
+        let segment = |i: usize| (points[i], points[(i + 1) % n]);
+        let total = (0..n)
+            .map(|i| {
+                let (a, b) = segment(i);
+                (b - a).norm()
+            })
+            .sum::<f32>();
+        if total == 0. {
+            return points[0];
+        }
+        let mut rest = t.rem_euclid(1.) * total;
+        for i in 0..n {
+            let (a, b) = segment(i);
+            let len = (b - a).norm();
+            if rest < len {
+                return a + (b - a) * (rest / len);
+            }
+            rest -= len;
+        }
+        points[0]
+    }
+
+    // The position at phase `t` in [0, 1) of one period
+    pub fn at(&self, t: f32) -> Point3 {
+        let theta = 2.0 * PI * t;
+        match self {
+            Self::Line { start, end } => Self::polyline_at(&[*start, *end], t),
+            Self::Circle { center, radius } => {
+                center + *radius * Vector3::new(theta.cos(), theta.sin(), 0.0)
+            }
+            Self::Lissajous {
+                center,
+                amplitude: (x, y),
+                freq: (a, b),
+                delta,
+            } => {
+                center
+                    + Vector3::new(
+                        x * (*a as f32 * theta + delta).sin(),
+                        y * (*b as f32 * theta).sin(),
+                        0.0,
+                    )
+            }
+            Self::Spiral {
+                center,
+                radius,
+                turns,
+            } => {
+                let r = radius * (1. - (2. * t.rem_euclid(1.) - 1.).abs());
+                let phi = theta * *turns as f32;
+                center + r * Vector3::new(phi.cos(), phi.sin(), 0.0)
+            }
+            Self::FigureEight { center, radius } => {
+                center + *radius * Vector3::new(theta.sin(), theta.sin() * theta.cos(), 0.0)
+            }
+            Self::Raster {
+                center,
+                width,
+                height,
+                lines,
+            } => Self::polyline_at(&Self::raster_corners(*center, *width, *height, *lines), t),
+            Self::Points(points) => Self::polyline_at(points, t),
+        }
+    }
+
+    pub fn points(&self, size: usize) -> Vec<Point3> {
+        (0..size).map(|i| self.at(i as f32 / size as f32)).collect()
+    }
+
+    // Length of one period, approximated by a fine polyline
+    pub fn length(&self) -> f32 {
+        let points = self.points(4096);
+        points
+            .iter()
+            .zip(points.iter().cycle().skip(1))
+            .map(|(a, b)| (b - a).norm())
+            .sum()
+    }
+
+    pub fn sampling_config(&self, size: usize, rate: Rate) -> SamplingConfig {
+        let freq = match rate {
+            Rate::Freq(freq) => freq,
+            Rate::Speed(speed) => speed / self.length() * Hz,
+        };
+        SamplingConfig::new(freq * size as f32).into_nearest()
+    }
+
+    pub fn foci_stm(
+        &self,
+        size: usize,
+        rate: Rate,
+    ) -> FociSTM<1, Vec<ControlPoint>, SamplingConfig> {
+        FociSTM::new(
+            self.points(size)
+                .into_iter()
+                .map(ControlPoint::from)
+                .collect(),
+            self.sampling_config(size, rate),
+        )
+    }
+
+    pub fn gain_stm(
+        &self,
+        size: usize,
+        rate: Rate,
+        option: GainSTMOption,
+    ) -> GainSTM<Vec<Focus>, SamplingConfig> {
+        GainSTM::new(
+            self.points(size)
+                .into_iter()
+                .map(|p| Focus::new(p, Default::default()))
+                .collect(),
+            self.sampling_config(size, rate),
+            option,
+        )
+    }
+}
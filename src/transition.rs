@@ -1,6 +1,6 @@
 use std::time::Duration;
 
-use crate::print_msg_and_wait_for_key;
+use crate::{print_msg_and_wait_for_key, trajectory::Trajectory};
 
 use autd3::{core::link::Link, driver::datagram::EmulateGPIOIn, prelude::*};
 
@@ -11,11 +11,10 @@ fn transition_test_focus_stm<L: Link>(
     let point_num = 200;
     let radius = 30.0 * mm;
     let gen_foci = || {
-        (0..point_num).map(|i| {
-            let theta = 2.0 * PI * i as f32 / point_num as f32;
-            let p = radius * Vector3::new(theta.cos(), theta.sin(), 0.0);
-            ControlPoints::<1>::from(ControlPoint::new(center + p, Phase::ZERO))
-        })
+        Trajectory::circle(center, radius)
+            .points(point_num)
+            .into_iter()
+            .map(|p| ControlPoints::<1>::from(ControlPoint::new(p, Phase::ZERO)))
     };
 
     let stm = FociSTM::new(gen_foci().collect::<Vec<_>>(), 0.5 * Hz);
@@ -107,11 +106,10 @@ fn transition_test_gain_stm<L: Link>(
     let point_num = 200;
     let radius = 30.0 * mm;
     let gen_foci = || {
-        (0..point_num).map(|i| {
-            let theta = 2.0 * PI * i as f32 / point_num as f32;
-            let p = radius * Vector3::new(theta.cos(), theta.sin(), 0.0);
-            Focus::new(center + p, Default::default())
-        })
+        Trajectory::circle(center, radius)
+            .points(point_num)
+            .into_iter()
+            .map(|p| Focus::new(p, Default::default()))
     };
 
     let stm = GainSTM::new(gen_foci().collect::<Vec<_>>(), 0.5 * Hz, Default::default());
@@ -194,9 +192,119 @@ fn transition_test_gain_stm<L: Link>(
     Ok(())
 }
 
+fn check_mod_segment<L: Link>(
+    autd: &mut Controller<L, firmware::V12_1>,
+    segment: Segment,
+) -> anyhow::Result<()> {
+    std::thread::sleep(Duration::from_millis(100));
+    autd.fpga_state()?.iter().for_each(|state| {
+        assert!(state.is_some());
+        assert_eq!(segment, state.unwrap().current_mod_segment());
+    });
+    Ok(())
+}
+
+fn transition_test_modulation<L: Link>(
+    autd: &mut Controller<L, firmware::V12_1>,
+) -> anyhow::Result<()> {
+    let center = autd.geometry().center() + Vector3::new(0., 0., 150.0 * mm);
+    let len = SamplingConfig::FREQ_4K.freq()?.hz() as usize;
+    let click = || autd3::modulation::Custom {
+        buffer: [vec![0xFF; 1], vec![0; len - 1]].concat(),
+        sampling_config: SamplingConfig::FREQ_4K,
+    };
+    let tone = || autd3::modulation::Custom {
+        buffer: (0..len)
+            .map(|i| if i % 20 < 10 { 0xFF } else { 0x00 })
+            .collect::<Vec<_>>(),
+        sampling_config: SamplingConfig::FREQ_4K,
+    };
+
+    autd.send((click(), Focus::new(center, Default::default())))?;
+    print_msg_and_wait_for_key("1秒に1回, 単発音が聞こえること");
+    check_mod_segment(autd, Segment::S0)?;
+
+    autd.send(WithLoopBehavior {
+        inner: tone(),
+        loop_behavior: LoopBehavior::ONCE,
+        segment: Segment::S1,
+        transition_mode: None,
+    })?;
+    print_msg_and_wait_for_key(
+        "何も変化していないこと\n次に, 単発音が聞こえたときにEnterを押し次のことを確認する\n2秒後(再び単発音が聞こえる時)に200Hzの音が1秒間だけ鳴り, その後無音になること",
+    );
+    check_mod_segment(autd, Segment::S0)?;
+    let transition_time = DcSysTime::now() + Duration::from_millis(2000);
+    autd.send(SwapSegment::Modulation(
+        Segment::S1,
+        TransitionMode::SysTime(transition_time),
+    ))?;
+    check_mod_segment(autd, Segment::S0)?;
+    print_msg_and_wait_for_key("");
+    std::thread::sleep(Duration::from_nanos(
+        transition_time
+            .sys_time()
+            .saturating_sub(DcSysTime::now().sys_time()),
+    ));
+    check_mod_segment(autd, Segment::S1)?;
+
+    [GPIOIn::I0, GPIOIn::I1, GPIOIn::I2, GPIOIn::I3]
+        .into_iter()
+        .try_for_each(|pin| -> anyhow::Result<()> {
+            autd.send((
+                SwapSegment::Modulation(Segment::S0, TransitionMode::Immediate),
+                EmulateGPIOIn::new(|_| |_| false),
+            ))?;
+            print_msg_and_wait_for_key("再び1秒に1回, 単発音が聞こえること");
+            check_mod_segment(autd, Segment::S0)?;
+
+            autd.send((
+                SwapSegment::Modulation(Segment::S1, TransitionMode::GPIO(pin)),
+                EmulateGPIOIn::new(move |_| move |gpio| gpio != pin),
+            ))?;
+            print_msg_and_wait_for_key(&format!(
+                "{:?}以外のGPIO入力では何も変化していないこと",
+                pin
+            ));
+            check_mod_segment(autd, Segment::S0)?;
+
+            autd.send(EmulateGPIOIn::new(move |_| move |gpio| gpio == pin))?;
+            print_msg_and_wait_for_key(&format!(
+                "{:?}の入力により直ちに200Hzの音が1秒間だけ鳴り, その後無音になること",
+                pin
+            ));
+            check_mod_segment(autd, Segment::S1)?;
+            Ok(())
+        })?;
+    autd.send(EmulateGPIOIn::new(|_| |_| false))?;
+
+    autd.send(click())?;
+    autd.send(WithSegment {
+        inner: tone(),
+        segment: Segment::S1,
+        transition_mode: Some(TransitionMode::Ext),
+    })?;
+    print_msg_and_wait_for_key("1秒ごとに単発音と200Hzの音が交互に鳴ること");
+
+    {
+        autd.send(Static::default())?;
+        let m = WithLoopBehavior {
+            inner: tone(),
+            loop_behavior: LoopBehavior::ONCE,
+            segment: Segment::S1,
+            transition_mode: Some(TransitionMode::SysTime(DcSysTime::now())),
+        };
+        assert_eq!(Err(AUTDDriverError::MissTransitionTime), autd.send(m));
+        check_mod_segment(autd, Segment::S0)?;
+    }
+
+    Ok(())
+}
+
 pub fn transition_test<L: Link>(autd: &mut Controller<L, firmware::V12_1>) -> anyhow::Result<()> {
     transition_test_focus_stm(autd)?;
     transition_test_gain_stm(autd)?;
+    transition_test_modulation(autd)?;
 
     Ok(())
 }
@@ -0,0 +1,343 @@
+use std::{collections::HashMap, num::NonZeroU16, time::Duration};
+
+use crate::{
+    assert_no_mismatches, audit, print_msg_and_wait_for_key, target::Target, trajectory::Trajectory,
+};
+
+use autd3::{core::link::Link, driver::datagram::EmulateGPIOIn, prelude::*};
+
+const PINS: [GPIOIn; 4] = [GPIOIn::I0, GPIOIn::I1, GPIOIn::I2, GPIOIn::I3];
+// The external trigger box is wired to this input
+const TRIGGER_PIN: GPIOIn = GPIOIn::I2;
+const REPEATS: usize = 8;
+
+fn mask(pins: impl IntoIterator<Item = GPIOIn>) -> u8 {
+    pins.into_iter().fold(0, |acc, pin| acc | 1 << pin as u8)
+}
+
+// Sets the emulated inputs of each device from the bit masks
+fn set_inputs<L: Link>(
+    autd: &mut Controller<L, firmware::V12_1>,
+    masks: &[u8],
+) -> anyhow::Result<()> {
+    autd.send(EmulateGPIOIn::new(|dev| {
+        let mask = masks[dev.idx()];
+        move |gpio| mask & 1 << gpio as u8 != 0
+    }))?;
+    Ok(())
+}
+
+fn write<L: Link>(
+    autd: &mut Controller<L, firmware::V12_1>,
+    target: Target,
+    segment: Segment,
+    loop_behavior: LoopBehavior,
+    transition_mode: Option<TransitionMode>,
+) -> anyhow::Result<()> {
+    let center = autd.geometry().center() + Vector3::new(0., 0., 150.0 * mm);
+    let config = SamplingConfig::new(NonZeroU16::new(40).unwrap());
+    let points = Trajectory::circle(center, 30.0 * mm).points(4);
+    match target {
+        Target::Modulation => autd.send(WithLoopBehavior {
+            inner: autd3::modulation::Custom {
+                buffer: match segment {
+                    Segment::S0 => vec![0xFF, 0xFF],
+                    Segment::S1 => vec![0xFF, 0x00],
+                },
+                sampling_config: config,
+            },
+            loop_behavior,
+            segment,
+            transition_mode,
+        })?,
+        Target::FociSTM => autd.send(WithLoopBehavior {
+            inner: FociSTM::new(points, config),
+            loop_behavior,
+            segment,
+            transition_mode,
+        })?,
+        Target::GainSTM => autd.send(WithLoopBehavior {
+            inner: GainSTM::new(
+                points
+                    .into_iter()
+                    .map(|p| Focus::new(p, Default::default()))
+                    .collect::<Vec<_>>(),
+                config,
+                Default::default(),
+            ),
+            loop_behavior,
+            segment,
+            transition_mode,
+        })?,
+    }
+    Ok(())
+}
+
+fn segments<L: Link>(
+    autd: &mut Controller<L, firmware::V12_1>,
+    target: Target,
+) -> anyhow::Result<Vec<Option<Segment>>> {
+    std::thread::sleep(Duration::from_millis(100));
+    Ok(autd
+        .fpga_state()?
+        .iter()
+        .map(|state| {
+            assert!(state.is_some());
+            let state = state.unwrap();
+            match target {
+                Target::Modulation => Some(state.current_mod_segment()),
+                Target::FociSTM | Target::GainSTM => state.current_stm_segment(),
+            }
+        })
+        .collect())
+}
+
+fn check<L: Link>(
+    autd: &mut Controller<L, firmware::V12_1>,
+    target: Target,
+    name: &str,
+    expect: &[Segment],
+    mismatches: &mut Vec<String>,
+) -> anyhow::Result<()> {
+    let expect = expect.iter().map(|&s| Some(s)).collect::<Vec<_>>();
+    let actual = segments(autd, target)?;
+    if expect != actual {
+        mismatches.push(format!(
+            "{:?}, {}: expected {:?}, actual {:?}",
+            target, name, expect, actual
+        ));
+    }
+    Ok(())
+}
+
+// Writes `segment` with a finite loop so that it can be the destination of a GPIO transition, and
+// arms the transition on each device with the pin given by `pin_of`
+fn arm<L: Link>(
+    autd: &mut Controller<L, firmware::V12_1>,
+    target: Target,
+    segment: Segment,
+    pin_of: impl Fn(usize) -> GPIOIn,
+) -> anyhow::Result<()> {
+    write(
+        autd,
+        target,
+        segment,
+        LoopBehavior::Finite(NonZeroU16::MAX),
+        None,
+    )?;
+    let keys = (0..autd.geometry().num_devices())
+        .map(|dev| pin_of(dev) as usize)
+        .collect::<Vec<_>>();
+    autd.send(Group::new(
+        |dev| Some(keys[dev.idx()]),
+        keys.iter()
+            .map(|&key| (key, target.swap(segment, TransitionMode::GPIO(PINS[key]))))
+            .collect::<HashMap<_, _>>(),
+    ))?;
+    Ok(())
+}
+
+fn reset<L: Link>(autd: &mut Controller<L, firmware::V12_1>, target: Target) -> anyhow::Result<()> {
+    set_inputs(autd, &vec![0; autd.geometry().num_devices()])?;
+    autd.send((Static::default(), Null::new()))?;
+    write(
+        autd,
+        target,
+        Segment::S0,
+        LoopBehavior::Infinite,
+        Some(TransitionMode::Immediate),
+    )?;
+    Ok(())
+}
+
+fn emulated<L: Link>(
+    autd: &mut Controller<L, firmware::V12_1>,
+    target: Target,
+    mismatches: &mut Vec<String>,
+) -> anyhow::Result<()> {
+    let num_devices = autd.geometry().num_devices();
+    let all = |segment: Segment| vec![segment; num_devices];
+
+    // Each input alone, with all the other inputs asserted as distractors
+    for pin in PINS {
+        reset(autd, target)?;
+        arm(autd, target, Segment::S1, |_| pin)?;
+        check(
+            autd,
+            target,
+            &format!("{:?} armed", pin),
+            &all(Segment::S0),
+            mismatches,
+        )?;
+        set_inputs(
+            autd,
+            &vec![mask(PINS.into_iter().filter(|&p| p != pin)); num_devices],
+        )?;
+        check(
+            autd,
+            target,
+            &format!("{:?} with distractors", pin),
+            &all(Segment::S0),
+            mismatches,
+        )?;
+        set_inputs(autd, &vec![mask([pin]); num_devices])?;
+        check(
+            autd,
+            target,
+            &format!("{:?} asserted", pin),
+            &all(Segment::S1),
+            mismatches,
+        )?;
+    }
+
+    // Each device waits on a different input; asserting one device's input must switch only the
+    // devices waiting on it
+    for rotation in 0..PINS.len() {
+        let pin_of = |dev: usize| PINS[(dev + rotation) % PINS.len()];
+        reset(autd, target)?;
+        arm(autd, target, Segment::S1, pin_of)?;
+        let mut expect = all(Segment::S0);
+        for dev in 0..num_devices {
+            set_inputs(autd, &vec![mask([pin_of(dev)]); num_devices])?;
+            (0..num_devices)
+                .filter(|&d| pin_of(d) == pin_of(dev))
+                .for_each(|d| expect[d] = Segment::S1);
+            check(
+                autd,
+                target,
+                &format!("rotation {}, {:?} of device {}", rotation, pin_of(dev), dev),
+                &expect,
+                mismatches,
+            )?;
+        }
+    }
+
+    // Repeated triggers back and forth between the segments
+    reset(autd, target)?;
+    for (n, segment) in [Segment::S1, Segment::S0]
+        .into_iter()
+        .cycle()
+        .take(REPEATS)
+        .enumerate()
+    {
+        let pin = PINS[n % PINS.len()];
+        let current = match segment {
+            Segment::S0 => Segment::S1,
+            Segment::S1 => Segment::S0,
+        };
+        set_inputs(autd, &vec![0; num_devices])?;
+        arm(autd, target, segment, |_| pin)?;
+        check(
+            autd,
+            target,
+            &format!("repeat {}, {:?} armed", n, pin),
+            &all(current),
+            mismatches,
+        )?;
+        set_inputs(autd, &vec![mask([pin]); num_devices])?;
+        check(
+            autd,
+            target,
+            &format!("repeat {}, {:?} asserted", n, pin),
+            &all(segment),
+            mismatches,
+        )?;
+    }
+
+    // The transition is level triggered, so an input that is already high switches as soon as the
+    // transition is armed
+    for pin in PINS {
+        reset(autd, target)?;
+        set_inputs(autd, &vec![mask([pin]); num_devices])?;
+        check(
+            autd,
+            target,
+            &format!("{:?} high before arming", pin),
+            &all(Segment::S0),
+            mismatches,
+        )?;
+        arm(autd, target, Segment::S1, |_| pin)?;
+        check(
+            autd,
+            target,
+            &format!("{:?} high when armed", pin),
+            &all(Segment::S1),
+            mismatches,
+        )?;
+    }
+
+    set_inputs(autd, &vec![0; num_devices])?;
+
+    Ok(())
+}
+
+fn physical<L: Link>(
+    autd: &mut Controller<L, firmware::V12_1>,
+    mismatches: &mut Vec<String>,
+) -> anyhow::Result<()> {
+    let num_devices = autd.geometry().num_devices();
+    autd.send(GPIOOutputs::new(|_dev, gpio| match gpio {
+        GPIOOut::O0 => Some(GPIOOutputType::ModSegment),
+        _ => None,
+    }))?;
+    for pin in PINS {
+        let input = if pin == TRIGGER_PIN {
+            format!("外部トリガボックスから{:?}", pin)
+        } else {
+            format!("GPIO入力{:?}", pin)
+        };
+        reset(autd, Target::Modulation)?;
+        print_msg_and_wait_for_key(
+            "全デバイスの全てのGPIO入力がLowであることを確認し, Enterを押す",
+        );
+        arm(autd, Target::Modulation, Segment::S1, |_| pin)?;
+        print_msg_and_wait_for_key(&format!(
+            "{:?}以外のGPIO入力をトグルしても各デバイスのGPIO[0]出力がLowのままであること\n確認後, 全てのGPIO入力をLowに戻しEnterを押す",
+            pin
+        ));
+        check(
+            autd,
+            Target::Modulation,
+            &format!("physical {:?} armed", pin),
+            &vec![Segment::S0; num_devices],
+            mismatches,
+        )?;
+        print_msg_and_wait_for_key(&format!(
+            "{}をHighにし, 各デバイスのGPIO[0]出力がHighになること\n確認後, Enterを押す",
+            input
+        ));
+        check(
+            autd,
+            Target::Modulation,
+            &format!("physical {:?} asserted", pin),
+            &vec![Segment::S1; num_devices],
+            mismatches,
+        )?;
+    }
+    print_msg_and_wait_for_key("全てのGPIO入力をLowに戻し, Enterを押す");
+    autd.send(GPIOOutputs::new(|_dev, gpio| match gpio {
+        GPIOOut::O0 => Some(GPIOOutputType::BaseSignal),
+        _ => None,
+    }))?;
+    Ok(())
+}
+
+pub fn transition_gpio_test<L: Link + 'static>(
+    autd: &mut Controller<L, firmware::V12_1>,
+) -> anyhow::Result<()> {
+    autd.send(Silencer::disable())?;
+
+    let mut mismatches = Vec::new();
+    for target in [Target::Modulation, Target::FociSTM, Target::GainSTM] {
+        emulated(autd, target, &mut mismatches)?;
+    }
+    if audit::emulators(autd).is_none() {
+        physical(autd, &mut mismatches)?;
+    }
+
+    assert_no_mismatches(&mismatches, "GPIO input transition test");
+
+    autd.send(Clear::new())?;
+
+    Ok(())
+}
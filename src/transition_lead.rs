@@ -0,0 +1,140 @@
+use std::{num::NonZeroU16, time::Duration};
+
+use crate::assert_no_mismatches;
+
+use autd3::{core::link::Link, prelude::*};
+
+const TRIALS: usize = 8;
+const SEND_INTERVALS: [Duration; 3] = [
+    Duration::from_micros(500),
+    Duration::from_millis(1),
+    Duration::from_millis(2),
+];
+// Lead times in microseconds; negative values schedule the transition in the past
+const PAST_LEADS: [i64; 3] = [-100_000, -10_000, -1_000];
+const LEAD_STEP: i64 = 500;
+const LEAD_MAX: i64 = 20_000;
+
+fn leads() -> impl Iterator<Item = i64> {
+    PAST_LEADS
+        .into_iter()
+        .chain((0..=LEAD_MAX / LEAD_STEP).map(|i| i * LEAD_STEP))
+}
+
+fn scheduled(lead: i64) -> DcSysTime {
+    let now = DcSysTime::now();
+    if lead < 0 {
+        now - Duration::from_micros(lead.unsigned_abs())
+    } else {
+        now + Duration::from_micros(lead as u64)
+    }
+}
+
+fn modulation(segment: Segment) -> autd3::modulation::Custom<SamplingConfig> {
+    autd3::modulation::Custom {
+        buffer: match segment {
+            Segment::S0 => vec![0xFF, 0xFF],
+            Segment::S1 => vec![0xFF, 0x00],
+        },
+        sampling_config: SamplingConfig::new(NonZeroU16::new(10).unwrap()),
+    }
+}
+
+// Returns whether the transition scheduled `lead` microseconds ahead was accepted
+fn try_lead<L: Link>(autd: &mut Controller<L, firmware::V12_1>, lead: i64) -> anyhow::Result<bool> {
+    autd.send(WithSegment {
+        inner: modulation(Segment::S0),
+        segment: Segment::S0,
+        transition_mode: Some(TransitionMode::Immediate),
+    })?;
+    autd.send(WithLoopBehavior {
+        inner: modulation(Segment::S1),
+        loop_behavior: LoopBehavior::Finite(NonZeroU16::MAX),
+        segment: Segment::S1,
+        transition_mode: None,
+    })?;
+    match autd.send(SwapSegment::Modulation(
+        Segment::S1,
+        TransitionMode::SysTime(scheduled(lead)),
+    )) {
+        Ok(()) => Ok(true),
+        Err(AUTDDriverError::MissTransitionTime) => Ok(false),
+        Err(e) => Err(e.into()),
+    }
+}
+
+// The smallest lead from which every larger lead in the sweep was accepted in all trials
+fn min_reliable_lead(results: &[(i64, usize)]) -> Option<i64> {
+    results
+        .iter()
+        .rev()
+        .take_while(|&&(_, accepted)| accepted == TRIALS)
+        .last()
+        .map(|&(lead, _)| lead)
+}
+
+pub fn transition_lead_test<L: Link>(
+    autd: &mut Controller<L, firmware::V12_1>,
+) -> anyhow::Result<()> {
+    let link = std::any::type_name::<L>();
+    let default_option = autd.default_sender_option;
+
+    let mut mismatches = Vec::new();
+    let mut summary = Vec::new();
+    for send_interval in SEND_INTERVALS {
+        autd.default_sender_option = SenderOption {
+            send_interval,
+            ..default_option
+        };
+        let results = leads()
+            .map(|lead| -> anyhow::Result<(i64, usize)> {
+                let accepted = (0..TRIALS)
+                    .map(|_| try_lead(autd, lead))
+                    .collect::<anyhow::Result<Vec<_>>>()?
+                    .into_iter()
+                    .filter(|&accepted| accepted)
+                    .count();
+                println!(
+                    "send interval {:?}, lead {:>7}us: {}/{} accepted",
+                    send_interval, lead, accepted, TRIALS
+                );
+                Ok((lead, accepted))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        // A transition scheduled now or in the past can never be taken
+        results
+            .iter()
+            .filter(|&&(lead, accepted)| lead <= 0 && accepted > 0)
+            .for_each(|(lead, accepted)| {
+                mismatches.push(format!(
+                    "send interval {:?}: lead {}us was accepted {}/{} times",
+                    send_interval, lead, accepted, TRIALS
+                ))
+            });
+        let min = min_reliable_lead(&results);
+        if min.is_none() {
+            mismatches.push(format!(
+                "send interval {:?}: no lead up to {}us was reliably accepted",
+                send_interval, LEAD_MAX
+            ));
+        }
+        summary.push((send_interval, min));
+    }
+    autd.default_sender_option = default_option;
+
+    summary.iter().for_each(|(send_interval, min)| {
+        println!(
+            "{}, send interval {:?}: minimum reliable lead {}",
+            link,
+            send_interval,
+            min.map_or("not found".to_string(), |lead| format!("{}us", lead))
+        );
+    });
+
+    assert_no_mismatches(&mismatches, "transition lead time test");
+
+    autd.send(Clear::new())?;
+
+    Ok(())
+}
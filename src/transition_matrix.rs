@@ -0,0 +1,293 @@
+use std::time::Duration;
+
+use autd3::{core::link::Link, prelude::*};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Write,
+    Swap,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Modulation,
+    Gain,
+    FociSTM,
+    GainSTM,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PlaybackState {
+    Gain,
+    FociSTM,
+    GainSTM,
+}
+
+// SysTime is only a marker here; the deadline is computed when the cell is executed, as running the
+// whole matrix on a real link takes far longer than any fixed lead time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Transition {
+    Immediate,
+    Ext,
+    SyncIdx,
+    SysTime,
+    Gpio(GPIOIn),
+}
+
+impl Transition {
+    fn mode(&self) -> TransitionMode {
+        match *self {
+            Transition::Immediate => TransitionMode::Immediate,
+            Transition::Ext => TransitionMode::Ext,
+            Transition::SyncIdx => TransitionMode::SyncIdx,
+            Transition::SysTime => {
+                TransitionMode::SysTime(DcSysTime::now() + Duration::from_millis(2000))
+            }
+            Transition::Gpio(gpio) => TransitionMode::GPIO(gpio),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Cell {
+    op: Op,
+    kind: Kind,
+    segment: Segment,
+    transition_mode: Option<Transition>,
+    loop_behavior: LoopBehavior,
+    state: PlaybackState,
+}
+
+const OPS: [Op; 2] = [Op::Write, Op::Swap];
+const KINDS: [Kind; 4] = [Kind::Modulation, Kind::Gain, Kind::FociSTM, Kind::GainSTM];
+const SEGMENTS: [Segment; 2] = [Segment::S0, Segment::S1];
+const LOOP_BEHAVIORS: [LoopBehavior; 2] = [LoopBehavior::Infinite, LoopBehavior::ONCE];
+const STATES: [PlaybackState; 3] = [
+    PlaybackState::Gain,
+    PlaybackState::FociSTM,
+    PlaybackState::GainSTM,
+];
+
+const TRANSITION_MODES: [Option<Transition>; 6] = [
+    None,
+    Some(Transition::Immediate),
+    Some(Transition::Ext),
+    Some(Transition::SyncIdx),
+    Some(Transition::SysTime),
+    Some(Transition::Gpio(GPIOIn::I0)),
+];
+
+// The current segment is always S0 and S0 always loops infinitely, so the loop behavior of the
+// requested segment matters only when switching to S1.
+fn transition_rule(
+    segment: Segment,
+    loop_behavior: LoopBehavior,
+    transition_mode: Transition,
+) -> Result<(), AUTDDriverError> {
+    match (
+        segment == Segment::S0 || loop_behavior == LoopBehavior::Infinite,
+        transition_mode,
+    ) {
+        (true, Transition::Immediate | Transition::Ext) => Ok(()),
+        (false, Transition::SyncIdx | Transition::SysTime | Transition::Gpio(_)) => Ok(()),
+        _ => Err(AUTDDriverError::InvalidTransitionMode),
+    }
+}
+
+fn expected(cell: &Cell) -> Result<(), AUTDDriverError> {
+    match (cell.op, cell.kind, cell.transition_mode) {
+        (Op::Write, Kind::Gain, None | Some(Transition::Immediate)) => Ok(()),
+        (Op::Write, Kind::Gain, Some(_)) => Err(AUTDDriverError::InvalidTransitionMode),
+        (Op::Write, _, None) => Ok(()),
+        (Op::Write, _, Some(mode)) => transition_rule(cell.segment, cell.loop_behavior, mode),
+        (Op::Swap, _, None) => unreachable!(),
+        (Op::Swap, Kind::Gain, Some(Transition::Immediate)) => match cell.state {
+            PlaybackState::Gain => Ok(()),
+            _ => Err(AUTDDriverError::InvalidSegmentTransition),
+        },
+        (Op::Swap, Kind::Gain, Some(_)) => Err(AUTDDriverError::InvalidTransitionMode),
+        (Op::Swap, Kind::FociSTM, Some(_)) if cell.state != PlaybackState::FociSTM => {
+            Err(AUTDDriverError::InvalidSegmentTransition)
+        }
+        (Op::Swap, Kind::GainSTM, Some(_)) if cell.state != PlaybackState::GainSTM => {
+            Err(AUTDDriverError::InvalidSegmentTransition)
+        }
+        (Op::Swap, _, Some(mode)) => transition_rule(cell.segment, cell.loop_behavior, mode),
+    }
+}
+
+fn cells() -> Vec<Cell> {
+    OPS.iter()
+        .flat_map(|&op| KINDS.iter().map(move |&kind| (op, kind)))
+        .flat_map(|(op, kind)| SEGMENTS.iter().map(move |&segment| (op, kind, segment)))
+        .flat_map(|(op, kind, segment)| {
+            TRANSITION_MODES
+                .into_iter()
+                .filter(move |mode| op == Op::Write || mode.is_some())
+                .map(move |transition_mode| (op, kind, segment, transition_mode))
+        })
+        .flat_map(|(op, kind, segment, transition_mode)| {
+            // Gain has no loop behavior
+            let loop_behaviors: &[LoopBehavior] = if op == Op::Write && kind == Kind::Gain {
+                &[LoopBehavior::Infinite]
+            } else {
+                &LOOP_BEHAVIORS
+            };
+            loop_behaviors
+                .iter()
+                .map(move |&loop_behavior| (op, kind, segment, transition_mode, loop_behavior))
+        })
+        .flat_map(|(op, kind, segment, transition_mode, loop_behavior)| {
+            STATES.iter().map(move |&state| Cell {
+                op,
+                kind,
+                segment,
+                transition_mode,
+                loop_behavior,
+                state,
+            })
+        })
+        .collect()
+}
+
+fn prepare<L: Link>(
+    autd: &mut Controller<L, firmware::V12_1>,
+    state: PlaybackState,
+    loop_behavior: LoopBehavior,
+) -> Result<(), AUTDDriverError> {
+    autd.send(Clear::new())?;
+    autd.send(WithLoopBehavior {
+        inner: Static::default(),
+        loop_behavior,
+        segment: Segment::S1,
+        transition_mode: None,
+    })?;
+    match state {
+        // Both segments already hold a gain after Clear
+        PlaybackState::Gain => Ok(()),
+        PlaybackState::FociSTM => {
+            autd.send(WithSegment {
+                inner: FociSTM::new(
+                    (0..2).map(|_| ControlPoint::default()).collect::<Vec<_>>(),
+                    1. * Hz,
+                ),
+                segment: Segment::S0,
+                transition_mode: Some(TransitionMode::Immediate),
+            })?;
+            autd.send(WithLoopBehavior {
+                inner: FociSTM::new(
+                    (0..2).map(|_| ControlPoint::default()).collect::<Vec<_>>(),
+                    1. * Hz,
+                ),
+                loop_behavior,
+                segment: Segment::S1,
+                transition_mode: None,
+            })
+        }
+        PlaybackState::GainSTM => {
+            autd.send(WithSegment {
+                inner: GainSTM::new(
+                    (0..2).map(|_| Null).collect::<Vec<_>>(),
+                    1. * Hz,
+                    Default::default(),
+                ),
+                segment: Segment::S0,
+                transition_mode: Some(TransitionMode::Immediate),
+            })?;
+            autd.send(WithLoopBehavior {
+                inner: GainSTM::new(
+                    (0..2).map(|_| Null).collect::<Vec<_>>(),
+                    1. * Hz,
+                    Default::default(),
+                ),
+                loop_behavior,
+                segment: Segment::S1,
+                transition_mode: None,
+            })
+        }
+    }
+}
+
+fn execute<L: Link>(
+    autd: &mut Controller<L, firmware::V12_1>,
+    cell: &Cell,
+) -> Result<(), AUTDDriverError> {
+    let Cell {
+        segment,
+        transition_mode,
+        loop_behavior,
+        ..
+    } = *cell;
+    let transition_mode = transition_mode.map(|t| t.mode());
+    match (cell.op, cell.kind) {
+        (Op::Write, Kind::Modulation) => autd.send(WithLoopBehavior {
+            inner: Sine::new(150. * Hz, Default::default()),
+            loop_behavior,
+            segment,
+            transition_mode,
+        }),
+        (Op::Write, Kind::Gain) => autd.send(WithSegment {
+            inner: Null,
+            segment,
+            transition_mode,
+        }),
+        (Op::Write, Kind::FociSTM) => autd.send(WithLoopBehavior {
+            inner: FociSTM::new(
+                (0..2).map(|_| ControlPoint::default()).collect::<Vec<_>>(),
+                1. * Hz,
+            ),
+            loop_behavior,
+            segment,
+            transition_mode,
+        }),
+        (Op::Write, Kind::GainSTM) => autd.send(WithLoopBehavior {
+            inner: GainSTM::new(
+                (0..2).map(|_| Null).collect::<Vec<_>>(),
+                1. * Hz,
+                Default::default(),
+            ),
+            loop_behavior,
+            segment,
+            transition_mode,
+        }),
+        (Op::Swap, kind) => {
+            let mode = transition_mode.unwrap();
+            autd.send(match kind {
+                Kind::Modulation => SwapSegment::Modulation(segment, mode),
+                Kind::Gain => SwapSegment::Gain(segment, mode),
+                Kind::FociSTM => SwapSegment::FociSTM(segment, mode),
+                Kind::GainSTM => SwapSegment::GainSTM(segment, mode),
+            })
+        }
+    }
+}
+
+pub fn transition_matrix_test<L: Link>(
+    autd: &mut Controller<L, firmware::V12_1>,
+) -> anyhow::Result<()> {
+    let cells = cells();
+    let mismatches = cells
+        .iter()
+        .map(|cell| -> anyhow::Result<_> {
+            prepare(autd, cell.state, cell.loop_behavior)?;
+            let expect = expected(cell);
+            let actual = execute(autd, cell);
+            Ok((expect != actual).then_some((*cell, expect, actual)))
+        })
+        .filter_map(Result::transpose)
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    mismatches.iter().for_each(|(cell, expect, actual)| {
+        println!("{:?}: expected {:?}, actual {:?}", cell, expect, actual);
+    });
+    assert!(
+        mismatches.is_empty(),
+        "{}/{} cells did not match the expected result",
+        mismatches.len(),
+        cells.len()
+    );
+
+    autd.send(Clear::new())?;
+
+    Ok(())
+}
@@ -0,0 +1,185 @@
+use std::{num::NonZeroU16, time::Duration};
+
+use anyhow::Context;
+
+use crate::{
+    assert_no_mismatches, audit, print_msg_and_wait_for_key, read_input, trajectory::Trajectory,
+};
+
+use autd3::{
+    core::{common::ULTRASOUND_PERIOD, link::Link},
+    prelude::*,
+};
+
+const TRIALS: usize = 4;
+// Scheduled swaps may land at most one ultrasound period apart across devices
+const SKEW_MAX_PERIODS: u64 = 1;
+// The switch must happen within this many ultrasound periods of the scheduled time
+const LATENCY_MAX_PERIODS: i64 = 1;
+const WINDOW_PERIODS: i64 = 8;
+
+fn send<L: Link>(
+    autd: &mut Controller<L, firmware::V12_1>,
+    segment: Segment,
+    loop_behavior: LoopBehavior,
+    transition_mode: Option<TransitionMode>,
+) -> anyhow::Result<()> {
+    let center = autd.geometry().center() + Vector3::new(0., 0., 150.0 * mm);
+    let config = SamplingConfig::new(NonZeroU16::new(10).unwrap());
+    autd.send((
+        WithLoopBehavior {
+            inner: autd3::modulation::Custom {
+                buffer: vec![0xFF, 0x80],
+                sampling_config: config,
+            },
+            loop_behavior,
+            segment,
+            transition_mode,
+        },
+        WithLoopBehavior {
+            inner: FociSTM::new(Trajectory::circle(center, 30.0 * mm).points(4), config),
+            loop_behavior,
+            segment,
+            transition_mode,
+        },
+    ))?;
+    Ok(())
+}
+
+// Steps every emulator through the same timeline and returns, for each device, the offsets of the
+// modulation and STM switches from the scheduled time in ultrasound periods. As every emulator sees
+// the same timestamps, this only measures the latency; the skew across devices is zero by
+// construction and is measured on hardware only
+fn switch_offsets<L: Link + 'static>(
+    autd: &mut Controller<L, firmware::V12_1>,
+    segment: Segment,
+    at: DcSysTime,
+) -> Option<Vec<(Option<i64>, Option<i64>)>> {
+    audit::emulators_mut(autd).map(|emulators| {
+        emulators
+            .iter_mut()
+            .map(|cpu| {
+                let (mut mod_offset, mut stm_offset) = (None, None);
+                (-WINDOW_PERIODS..=WINDOW_PERIODS).for_each(|k| {
+                    let t = if k < 0 {
+                        at - ULTRASOUND_PERIOD * k.unsigned_abs() as u32
+                    } else {
+                        at + ULTRASOUND_PERIOD * k as u32
+                    };
+                    cpu.update_with_sys_time(t);
+                    let fpga = cpu.fpga();
+                    if mod_offset.is_none() && fpga.current_mod_segment() == segment {
+                        mod_offset = Some(k);
+                    }
+                    if stm_offset.is_none() && fpga.current_stm_segment() == segment {
+                        stm_offset = Some(k);
+                    }
+                });
+                (mod_offset, stm_offset)
+            })
+            .collect()
+    })
+}
+
+pub fn transition_sync_test<L: Link + 'static>(
+    autd: &mut Controller<L, firmware::V12_1>,
+) -> anyhow::Result<()> {
+    autd.send(Silencer::disable())?;
+    autd.send(GPIOOutputs::new(|_dev, gpio| match gpio {
+        GPIOOut::O0 => Some(GPIOOutputType::StmSegment),
+        GPIOOut::O1 => Some(GPIOOutputType::ModSegment),
+        _ => None,
+    }))?;
+    send(
+        autd,
+        Segment::S0,
+        LoopBehavior::Infinite,
+        Some(TransitionMode::Immediate),
+    )?;
+
+    let mut clock = DcSysTime::now();
+    let mut mismatches = Vec::new();
+    for (trial, segment) in [Segment::S1, Segment::S0]
+        .into_iter()
+        .cycle()
+        .take(TRIALS)
+        .enumerate()
+    {
+        send(autd, segment, LoopBehavior::Finite(NonZeroU16::MAX), None)?;
+
+        if audit::emulators(autd).is_none() {
+            print_msg_and_wait_for_key(
+                "オシロスコープを各デバイスのGPIO[0]とGPIO[1]のエッジでシングルトリガに設定し, Enterを押す",
+            );
+        }
+        // Simulated time must not go backwards, or pending transitions are never taken
+        let at = if DcSysTime::now().sys_time() > clock.sys_time() {
+            DcSysTime::now()
+        } else {
+            clock
+        } + Duration::from_millis(500);
+        clock = at + ULTRASOUND_PERIOD * WINDOW_PERIODS as u32;
+        autd.send((
+            SwapSegment::Modulation(segment, TransitionMode::SysTime(at)),
+            SwapSegment::FociSTM(segment, TransitionMode::SysTime(at)),
+        ))?;
+
+        match switch_offsets(autd, segment, at) {
+            Some(offsets) => {
+                println!(
+                    "trial {}, {:?}: latency from the scheduled time {:?}",
+                    trial, segment, offsets
+                );
+                let mut check = |name: &str, offsets: Vec<Option<i64>>| {
+                    let Some(offsets) = offsets.into_iter().collect::<Option<Vec<_>>>() else {
+                        mismatches.push(format!(
+                            "trial {}, {}: some devices did not switch to {:?}",
+                            trial, name, segment
+                        ));
+                        return;
+                    };
+                    if offsets.iter().any(|o| o.abs() > LATENCY_MAX_PERIODS) {
+                        mismatches.push(format!(
+                            "trial {}, {}: latency {:?} periods",
+                            trial, name, offsets
+                        ));
+                    }
+                };
+                check("modulation", offsets.iter().map(|(m, _)| *m).collect());
+                check("STM", offsets.iter().map(|(_, s)| *s).collect());
+            }
+            None => {
+                std::thread::sleep(Duration::from_millis(1000));
+                let skew = read_input("各デバイスのGPIO[0]とGPIO[1]のエッジの最大のずれ[us]")?;
+                let skew = skew
+                    .parse::<f32>()
+                    .with_context(|| format!("エッジのずれの値が不正です: {:?}", skew))?;
+                let bound = (ULTRASOUND_PERIOD * SKEW_MAX_PERIODS as u32).as_secs_f32() * 1e6;
+                if skew > bound {
+                    mismatches.push(format!(
+                        "trial {}: measured skew {}us exceeds {}us",
+                        trial, skew, bound
+                    ));
+                }
+            }
+        }
+
+        std::thread::sleep(Duration::from_millis(100));
+        autd.fpga_state()?.iter().for_each(|state| {
+            assert!(state.is_some());
+            let state = state.unwrap();
+            assert_eq!(segment, state.current_mod_segment());
+            assert_eq!(Some(segment), state.current_stm_segment());
+        });
+    }
+
+    assert_no_mismatches(&mismatches, "scheduled transition simultaneity test");
+
+    autd.send(GPIOOutputs::new(|_dev, gpio| match gpio {
+        GPIOOut::O0 => Some(GPIOOutputType::BaseSignal),
+        _ => None,
+    }))?;
+    autd.send(Clear::new())?;
+
+    Ok(())
+}